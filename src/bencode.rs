@@ -0,0 +1,391 @@
+use crate::torrent::TorrentFile;
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use std::collections::BTreeMap;
+
+/// Lists and dictionaries nested deeper than this are rejected, a .torrent file
+/// needs a handful of levels.
+const MAX_DEPTH: usize = 32;
+
+/// A bencoded value, as found in .torrent files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bencode {
+    Int(i64),
+    Bytes(Vec<u8>),
+    List(Vec<Bencode>),
+    Dict(BTreeMap<Vec<u8>, Bencode>),
+}
+
+impl Bencode {
+    pub fn decode(data: &[u8]) -> Result<Bencode, Box<dyn std::error::Error>> {
+        let (value, end) = decode_at(data, 0, 0)?;
+        if end != data.len() {
+            return Err(format!("Trailing data after bencoded value at {}", end).into());
+        }
+        Ok(value)
+    }
+
+    /// Encodes a value, dictionary keys sorted. Decoding then encoding a canonical
+    /// file gives back the same bytes.
+    #[allow(dead_code)]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode_into(&mut out);
+        out
+    }
+
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        match self {
+            Bencode::Int(i) => out.extend_from_slice(format!("i{}e", i).as_bytes()),
+            Bencode::Bytes(bytes) => {
+                out.extend_from_slice(format!("{}:", bytes.len()).as_bytes());
+                out.extend_from_slice(bytes);
+            }
+            Bencode::List(list) => {
+                out.push(b'l');
+                list.iter().for_each(|v| v.encode_into(out));
+                out.push(b'e');
+            }
+            Bencode::Dict(dict) => {
+                out.push(b'd');
+                for (key, value) in dict {
+                    Bencode::Bytes(key.clone()).encode_into(out);
+                    value.encode_into(out);
+                }
+                out.push(b'e');
+            }
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Bencode> {
+        match self {
+            Bencode::Dict(dict) => dict.get(key.as_bytes()),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Bencode::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// A size or a length, which can't be negative.
+    pub fn as_length(&self) -> Option<u64> {
+        self.as_int().and_then(|i| u64::try_from(i).ok())
+    }
+
+    pub fn as_str(&self) -> Option<String> {
+        match self {
+            Bencode::Bytes(bytes) => Some(String::from_utf8_lossy(bytes).into_owned()),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&Vec<Bencode>> {
+        match self {
+            Bencode::List(list) => Some(list),
+            _ => None,
+        }
+    }
+}
+
+/// Decodes the value starting at `pos`, returning it with the position right after it.
+/// `depth` is the number of lists and dictionaries the value is nested in.
+fn decode_at(
+    data: &[u8],
+    pos: usize,
+    depth: usize,
+) -> Result<(Bencode, usize), Box<dyn std::error::Error>> {
+    if depth > MAX_DEPTH {
+        return Err(format!("Bencoded data nested too deep at {}", pos).into());
+    }
+    match data.get(pos) {
+        Some(b'i') => {
+            let end = find(data, pos + 1, b'e')?;
+            let value = std::str::from_utf8(&data[pos + 1..end])?.parse::<i64>()?;
+            Ok((Bencode::Int(value), end + 1))
+        }
+        Some(b'l') => {
+            let mut list = Vec::new();
+            let mut pos = pos + 1;
+            while data.get(pos) != Some(&b'e') {
+                let (value, next) = decode_at(data, pos, depth + 1)?;
+                list.push(value);
+                pos = next;
+            }
+            Ok((Bencode::List(list), pos + 1))
+        }
+        Some(b'd') => {
+            let mut dict = BTreeMap::new();
+            let mut pos = pos + 1;
+            while data.get(pos) != Some(&b'e') {
+                let (key, next) = decode_at(data, pos, depth + 1)?;
+                let Bencode::Bytes(key) = key else {
+                    return Err(format!("Dictionary key is not a string at {}", pos).into());
+                };
+                let (value, next) = decode_at(data, next, depth + 1)?;
+                dict.insert(key, value);
+                pos = next;
+            }
+            Ok((Bencode::Dict(dict), pos + 1))
+        }
+        Some(b'0'..=b'9') => {
+            let colon = find(data, pos, b':')?;
+            let len = std::str::from_utf8(&data[pos..colon])?.parse::<usize>()?;
+            let start = colon + 1;
+            let end = start
+                .checked_add(len)
+                .ok_or("Bencoded string length out of range")?;
+            let bytes = data
+                .get(start..end)
+                .ok_or("Unexpected end of bencoded string")?;
+            Ok((Bencode::Bytes(bytes.to_vec()), end))
+        }
+        Some(c) => Err(format!("Invalid bencode token '{}' at {}", *c as char, pos).into()),
+        None => Err("Unexpected end of bencoded data".into()),
+    }
+}
+
+fn find(data: &[u8], from: usize, byte: u8) -> Result<usize, Box<dyn std::error::Error>> {
+    data.get(from..)
+        .and_then(|rest| rest.iter().position(|&c| c == byte))
+        .map(|idx| from + idx)
+        .ok_or_else(|| {
+            format!(
+                "Unexpected end of bencoded data, '{}' expected",
+                byte as char
+            )
+            .into()
+        })
+}
+
+/// Returns the raw bytes of the `info` dictionary, which the infohash is computed
+/// from. Hashing the original bytes keeps the hash right for non canonical files.
+fn raw_info(data: &[u8]) -> Result<&[u8], Box<dyn std::error::Error>> {
+    if data.first() != Some(&b'd') {
+        return Err("Torrent file is not a bencoded dictionary".into());
+    }
+
+    let mut pos = 1;
+    while data.get(pos) != Some(&b'e') {
+        let (key, next) = decode_at(data, pos, 1)?;
+        let (_, end) = decode_at(data, next, 1)?;
+        if key == Bencode::Bytes(b"info".to_vec()) {
+            return Ok(&data[next..end]);
+        }
+        pos = end;
+    }
+
+    Err("Info dictionary not found in torrent file".into())
+}
+
+#[derive(Debug, Serialize)]
+pub struct TorrentMeta {
+    pub name: String,
+    pub infohash: String,
+    pub size: u64,
+    pub piece_length: u64,
+    pub pieces: usize,
+    pub private: bool,
+    pub announce: Option<String>,
+    pub announce_list: Vec<Vec<String>>,
+    pub files: Vec<TorrentFile>,
+    pub comment: Option<String>,
+    pub created_by: Option<String>,
+    pub creation_date: Option<i64>,
+}
+
+impl TorrentMeta {
+    pub fn from_bytes(data: &[u8]) -> Result<TorrentMeta, Box<dyn std::error::Error>> {
+        let torrent = Bencode::decode(data)?;
+        let info = torrent
+            .get("info")
+            .ok_or("Info dictionary not found in torrent file")?;
+
+        let infohash = Sha1::digest(raw_info(data)?)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+
+        let name = info
+            .get("name.utf-8")
+            .or(info.get("name"))
+            .and_then(Bencode::as_str)
+            .ok_or("Torrent name not found")?;
+
+        let files = match info.get("files").and_then(Bencode::as_list) {
+            Some(files) => files
+                .iter()
+                .map(|file| {
+                    let path = file
+                        .get("path.utf-8")
+                        .or(file.get("path"))
+                        .and_then(Bencode::as_list)
+                        .ok_or("File path not found")?
+                        .iter()
+                        .filter_map(Bencode::as_str)
+                        .collect::<Vec<_>>()
+                        .join("/");
+                    let size = file
+                        .get("length")
+                        .and_then(Bencode::as_length)
+                        .ok_or("File length not found")?;
+                    Ok(TorrentFile { name: path, size })
+                })
+                .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?,
+            None => vec![TorrentFile {
+                name: name.clone(),
+                size: info
+                    .get("length")
+                    .and_then(Bencode::as_length)
+                    .ok_or("Torrent length not found")?,
+            }],
+        };
+
+        let size = files
+            .iter()
+            .try_fold(0u64, |total, file| total.checked_add(file.size))
+            .ok_or("Torrent size out of range")?;
+
+        let pieces = match info.get("pieces") {
+            Some(Bencode::Bytes(pieces)) => pieces.len() / 20,
+            _ => 0,
+        };
+
+        let announce_list = torrent
+            .get("announce-list")
+            .and_then(Bencode::as_list)
+            .map(|tiers| {
+                tiers
+                    .iter()
+                    .filter_map(Bencode::as_list)
+                    .map(|tier| tier.iter().filter_map(Bencode::as_str).collect())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(TorrentMeta {
+            name,
+            infohash,
+            size,
+            piece_length: info
+                .get("piece length")
+                .and_then(Bencode::as_length)
+                .unwrap_or_default(),
+            pieces,
+            private: info.get("private").and_then(Bencode::as_int) == Some(1),
+            announce: torrent.get("announce").and_then(Bencode::as_str),
+            announce_list,
+            files,
+            comment: torrent.get("comment").and_then(Bencode::as_str),
+            created_by: torrent.get("created by").and_then(Bencode::as_str),
+            creation_date: torrent.get("creation date").and_then(Bencode::as_int),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_TORRENT: &[u8] = include_bytes!("../tests/test.torrent");
+
+    #[test]
+    fn test_roundtrip() {
+        let value = Bencode::decode(TEST_TORRENT).unwrap();
+        assert_eq!(value.encode(), TEST_TORRENT);
+
+        let data = b"d3:bar4:spam3:fooi42e4:listl1:ai-1eee";
+        assert_eq!(Bencode::decode(data).unwrap().encode(), data);
+
+        let value = Bencode::Dict(BTreeMap::from([
+            (b"b".to_vec(), Bencode::Int(0)),
+            (
+                b"a".to_vec(),
+                Bencode::List(vec![Bencode::Bytes(vec![0, 255])]),
+            ),
+        ]));
+        assert_eq!(value.encode(), b"d1:al2:\x00\xffe1:bi0ee");
+        assert_eq!(Bencode::decode(&value.encode()).unwrap(), value);
+    }
+
+    #[test]
+    fn test_decode() {
+        let value = Bencode::decode(b"d3:bar4:spam3:fooi42e4:listl1:ai-1eee").unwrap();
+        assert_eq!(value.get("foo"), Some(&Bencode::Int(42)));
+        assert_eq!(
+            value.get("bar").and_then(Bencode::as_str),
+            Some("spam".into())
+        );
+        assert_eq!(
+            value.get("list").and_then(Bencode::as_list).unwrap().len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        assert!(Bencode::decode(b"i42").is_err());
+        assert!(Bencode::decode(b"5:spam").is_err());
+        assert!(Bencode::decode(b"di1e3:fooe").is_err());
+        assert!(Bencode::decode(b"i42ee").is_err());
+        assert!(TorrentMeta::from_bytes(b"<html></html>").is_err());
+
+        let nested = [vec![b'l'; 10000], vec![b'e'; 10000]].concat();
+        assert!(Bencode::decode(&nested).is_err());
+        let nested = [vec![b'l'; MAX_DEPTH], vec![b'e'; MAX_DEPTH]].concat();
+        assert!(Bencode::decode(&nested).is_ok());
+
+        let negative = b"d4:infod6:lengthi-1e4:name4:test12:piece lengthi16384eee";
+        assert!(TorrentMeta::from_bytes(negative).is_err());
+
+        assert!(Bencode::decode(b"18446744073709551615:spam").is_err());
+        let files = format!(
+            "d4:infod5:filesl{file}{file}{file}e4:name4:testee",
+            file = format!("d6:lengthi{}e4:pathl1:aee", i64::MAX)
+        );
+        assert!(TorrentMeta::from_bytes(files.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_torrent_meta() {
+        let meta = TorrentMeta::from_bytes(TEST_TORRENT).unwrap();
+        assert_eq!(meta.name, "test_folder");
+        assert_eq!(meta.infohash, "d984f67af9917b214cd8b6048ab5624c7df6a07a");
+        assert_eq!(meta.piece_length, 32768);
+        assert_eq!(meta.pieces, 589);
+        assert!(!meta.private);
+        assert_eq!(
+            meta.announce.as_deref(),
+            Some("https://academictorrents.com/announce.php")
+        );
+        assert_eq!(
+            meta.announce_list,
+            vec![
+                vec!["https://academictorrents.com/announce.php"],
+                vec!["udp://tracker.opentrackr.org:1337/announce"],
+            ]
+        );
+        assert_eq!(
+            meta.files,
+            vec![
+                TorrentFile {
+                    name: "images/LOC_Main_Reading_Room_Highsmith.jpg".to_string(),
+                    size: 17614527,
+                },
+                TorrentFile {
+                    name: "images/melk-abbey-library.jpg".to_string(),
+                    size: 1682177,
+                },
+                TorrentFile {
+                    name: "README".to_string(),
+                    size: 20,
+                },
+            ]
+        );
+        assert_eq!(meta.size, 19296724);
+        assert_eq!(meta.creation_date, Some(1495908054));
+    }
+}
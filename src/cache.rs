@@ -0,0 +1,195 @@
+use crate::parser::Torrent;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Default lifetime of a cached search page, in seconds.
+pub const DEFAULT_TTL: u64 = 300;
+
+/// Default number of cached search pages.
+pub const DEFAULT_MAX_ENTRIES: usize = 500;
+
+static SEARCH_CACHE: OnceLock<SearchCache> = OnceLock::new();
+
+/// In-process cache of YGG search pages, keyed by the search URL.
+pub struct SearchCache {
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<HashMap<String, (Instant, Vec<Torrent>)>>,
+}
+
+impl SearchCache {
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            ttl,
+            max_entries,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.ttl.is_zero() && self.max_entries > 0
+    }
+
+    pub fn get(&self, key: &str) -> Option<Vec<Torrent>> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let mut entries = self.entries.lock().ok()?;
+        match entries.get(key) {
+            Some((stored, torrents)) if stored.elapsed() < self.ttl => Some(torrents.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, key: String, torrents: Vec<Torrent>) {
+        if !self.is_enabled() {
+            return;
+        }
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            entries.retain(|_, (stored, _)| stored.elapsed() < self.ttl);
+        }
+        while entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, (stored, _))| *stored)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(oldest) => entries.remove(&oldest),
+                None => break,
+            };
+        }
+        entries.insert(key, (Instant::now(), torrents));
+    }
+}
+
+pub fn init_search_cache(ttl: u64, max_entries: usize) {
+    let _ = SEARCH_CACHE.set(SearchCache::new(Duration::from_secs(ttl), max_entries));
+}
+
+pub fn search_cache() -> &'static SearchCache {
+    SEARCH_CACHE
+        .get_or_init(|| SearchCache::new(Duration::from_secs(DEFAULT_TTL), DEFAULT_MAX_ENTRIES))
+}
+
+/// Outcome of the cache lookups made while serving a request (`X-Cache` header).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheStatus {
+    Hit,
+    Miss,
+    Bypass,
+}
+
+impl CacheStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            CacheStatus::Hit => "HIT",
+            CacheStatus::Miss => "MISS",
+            CacheStatus::Bypass => "BYPASS",
+        }
+    }
+}
+
+struct CacheScope {
+    bypass: bool,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+tokio::task_local! {
+    static CACHE_SCOPE: CacheScope;
+}
+
+/// Runs a request with its cache options, and reports whether its YGG searches
+/// were all served from the cache. With `bypass`, fresh results are fetched (and
+/// still stored for the next requests).
+pub async fn with_cache_scope<F: Future>(bypass: bool, fut: F) -> (F::Output, CacheStatus) {
+    let scope = CacheScope {
+        bypass,
+        hits: AtomicUsize::new(0),
+        misses: AtomicUsize::new(0),
+    };
+    CACHE_SCOPE
+        .scope(scope, async {
+            let output = fut.await;
+            let status = CACHE_SCOPE.with(|scope| {
+                if scope.bypass {
+                    CacheStatus::Bypass
+                } else if scope.misses.load(Ordering::Relaxed) == 0
+                    && scope.hits.load(Ordering::Relaxed) > 0
+                {
+                    CacheStatus::Hit
+                } else {
+                    CacheStatus::Miss
+                }
+            });
+            (output, status)
+        })
+        .await
+}
+
+/// Cached results of a search URL, unless the current request bypasses the cache.
+pub fn lookup(url: &str) -> Option<Vec<Torrent>> {
+    if CACHE_SCOPE.try_with(|scope| scope.bypass).unwrap_or(false) {
+        return None;
+    }
+    let torrents = search_cache().get(url)?;
+    let _ = CACHE_SCOPE.try_with(|scope| scope.hits.fetch_add(1, Ordering::Relaxed));
+    Some(torrents)
+}
+
+/// Stores the results freshly fetched for a search URL.
+pub fn store(url: &str, torrents: &[Torrent]) {
+    let _ = CACHE_SCOPE.try_with(|scope| scope.misses.fetch_add(1, Ordering::Relaxed));
+    search_cache().insert(url.to_string(), torrents.to_vec());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::torrent;
+
+    #[test]
+    fn test_search_cache() {
+        let cache = SearchCache::new(Duration::from_secs(60), 2);
+        cache.insert("a".to_string(), vec![torrent(1)]);
+        cache.insert("b".to_string(), vec![torrent(2)]);
+        assert_eq!(cache.get("a"), Some(vec![torrent(1)]));
+
+        // The oldest entry is evicted when the cache is full
+        cache.insert("c".to_string(), vec![torrent(3)]);
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.get("b"), Some(vec![torrent(2)]));
+        assert_eq!(cache.get("c"), Some(vec![torrent(3)]));
+
+        let disabled = SearchCache::new(Duration::ZERO, 2);
+        disabled.insert("a".to_string(), vec![torrent(1)]);
+        assert_eq!(disabled.get("a"), None);
+    }
+
+    #[tokio::test]
+    async fn test_cache_scope() {
+        let url = "https://example.org/engine/search?name=test_cache_scope&do=search";
+
+        let (found, status) = with_cache_scope(false, async { lookup(url) }).await;
+        assert_eq!((found, status), (None, CacheStatus::Miss));
+
+        let ((), status) = with_cache_scope(false, async { store(url, &[torrent(1)]) }).await;
+        assert_eq!(status, CacheStatus::Miss);
+
+        let (found, status) = with_cache_scope(false, async { lookup(url) }).await;
+        assert_eq!((found, status), (Some(vec![torrent(1)]), CacheStatus::Hit));
+
+        let (found, status) = with_cache_scope(true, async { lookup(url) }).await;
+        assert_eq!((found, status), (None, CacheStatus::Bypass));
+    }
+}
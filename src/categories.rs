@@ -1,17 +1,258 @@
 use crate::DOMAIN;
 use crate::search::get_rate_limiter;
+use crate::utils::check_session_expired;
 use crate::ygg_client::YggClient;
 use scraper::{Html, Selector};
 use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
 use tokio::sync::OnceCell;
 
 pub static CATEGORIES_CACHE: OnceCell<Vec<Category>> = OnceCell::const_new();
 
-#[derive(Debug, Serialize, Clone)]
+/// Advanced search options of the sub-categories, loaded on first use.
+static CATEGORY_OPTIONS: RwLock<BTreeMap<usize, Vec<CategoryOption>>> =
+    RwLock::new(BTreeMap::new());
+
+#[derive(Debug, Default, Serialize, Clone)]
 pub struct Category {
     pub id: usize,
     pub name: String,
     pub sub_categories: Vec<Category>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<CategoryOption>,
+}
+
+/// Advanced search option of a sub-category (language, quality, system...).
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct CategoryOption {
+    /// `/search` parameter (`option_langue`)
+    pub name: String,
+    /// YGG query parameter (`option_langue:multiple[]`)
+    pub param: String,
+    pub label: String,
+    pub values: Vec<OptionValue>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct OptionValue {
+    pub id: String,
+    pub label: String,
+}
+
+/// `(ygg_id, newznab_id, sub_categories)`
+pub type NewznabMapping = (usize, usize, &'static [(usize, usize)]);
+
+/// YGG categories mapped to the standard Newznab categories. Sub-categories carry
+/// the most specific Newznab category.
+pub const NEWZNAB_MAPPING: &[NewznabMapping] = &[
+    // Film/Vidéo
+    (
+        2145,
+        5000,
+        &[
+            (2178, 2020), // Animation: Movies/Other
+            (2179, 5070), // Animation Série: TV/Anime
+            (2180, 3020), // Concert: Audio/Video
+            (2181, 5080), // Documentaire: TV/Documentary
+            (2182, 5000), // Emission TV: TV
+            (2183, 2000), // Film: Movies
+            (2184, 5000), // Série TV: TV
+            (2185, 5000), // Spectacle: TV
+            (2186, 5060), // Sport: TV/Sport
+            (2187, 5050), // Vidéo-clips: TV/Other
+        ],
+    ),
+    // Audio
+    (
+        2139,
+        3000,
+        &[
+            (2147, 3000), // Karaoké: Audio
+            (2148, 3000), // Musique: Audio
+            (2150, 3000), // Podcast Radio: Audio
+            (2149, 3050), // Samples: Audio/Other
+        ],
+    ),
+    // Application
+    (
+        2144,
+        4000,
+        &[
+            (2177, 4010), // Autre: PC/0day
+            (2176, 4000), // Formation: PC
+            (2171, 4020), // Linux: PC/ISO
+            (2172, 4030), // MacOS: PC/Mac
+            (2174, 4070), // Smartphone: PC/Mobile-Android
+            (2175, 4070), // Tablette: PC/Mobile-Android
+            (2173, 4010), // Windows: PC/0day
+        ],
+    ),
+    // Jeu vidéo
+    (
+        2142,
+        4050,
+        &[
+            (2167, 1090), // Autre: Console/Other
+            (2159, 4050), // Linux: PC/Games
+            (2160, 4050), // MacOS: PC/Games
+            (2162, 1140), // Microsoft: Console/XBox One
+            (2163, 1030), // Nintendo: Console/Wii
+            (2165, 4070), // Smartphone: PC/Mobile-Android
+            (2164, 1180), // Sony: Console/PS4
+            (2166, 4070), // Tablette: PC/Mobile-Android
+            (2161, 4050), // Windows: PC/Games
+        ],
+    ),
+    // eBook
+    (
+        2140,
+        7000,
+        &[
+            (2151, 3030), // Audio: Audio/Audiobook
+            (2152, 7020), // Bds: Books/EBook
+            (2153, 7030), // Comics: Books/Comics
+            (2154, 7020), // Livres: Books/EBook
+            (2155, 7030), // Mangas: Books/Comics
+            (2156, 7010), // Presse: Books/Mags
+        ],
+    ),
+    // Nulled
+    (
+        2300,
+        8000,
+        &[(2301, 8000), (2302, 8000), (2303, 8000), (2304, 8000)],
+    ),
+    // Imprimante 3D
+    (2200, 8000, &[(2201, 8000), (2202, 8000)]),
+    // Emulation
+    (2141, 8000, &[(2157, 8000), (2158, 8000)]),
+    // GPS
+    (2143, 8000, &[(2168, 8000), (2169, 8000), (2170, 8000)]),
+    // XXX
+    (
+        2188,
+        6000,
+        &[
+            (2401, 6070), // Ebooks: XXX/Other
+            (2189, 6000), // Films: XXX
+            (2190, 6000), // Hentai: XXX
+            (2191, 6060), // Images: XXX/ImageSet
+            (2402, 6070), // Jeux: XXX/Other
+        ],
+    ),
+];
+
+/// Names of the standard Newznab categories used in `NEWZNAB_MAPPING`.
+pub const NEWZNAB_NAMES: &[(usize, &str)] = &[
+    (1000, "Console"),
+    (1030, "Console/Wii"),
+    (1090, "Console/Other"),
+    (1140, "Console/XBox One"),
+    (1180, "Console/PS4"),
+    (2000, "Movies"),
+    (2020, "Movies/Other"),
+    (3000, "Audio"),
+    (3020, "Audio/Video"),
+    (3030, "Audio/Audiobook"),
+    (3050, "Audio/Other"),
+    (4000, "PC"),
+    (4010, "PC/0day"),
+    (4020, "PC/ISO"),
+    (4030, "PC/Mac"),
+    (4050, "PC/Games"),
+    (4070, "PC/Mobile-Android"),
+    (5000, "TV"),
+    (5050, "TV/Other"),
+    (5060, "TV/Sport"),
+    (5070, "TV/Anime"),
+    (5080, "TV/Documentary"),
+    (6000, "XXX"),
+    (6060, "XXX/ImageSet"),
+    (6070, "XXX/Other"),
+    (7000, "Books"),
+    (7010, "Books/Mags"),
+    (7020, "Books/EBook"),
+    (7030, "Books/Comics"),
+    (8000, "Other"),
+];
+
+/// Newznab categories of a YGG category: the mapped category, then its parent
+/// (`2020` → `[2020, 2000]`).
+pub fn newznab_categories(ygg_id: usize) -> Vec<usize> {
+    let newznab_id =
+        NEWZNAB_MAPPING
+            .iter()
+            .find_map(|(id, newznab_id, subs)| match *id == ygg_id {
+                true => Some(*newznab_id),
+                false => subs
+                    .iter()
+                    .find(|(sub_id, _)| *sub_id == ygg_id)
+                    .map(|(_, newznab_id)| *newznab_id),
+            });
+    match newznab_id {
+        Some(id) if !id.is_multiple_of(1000) => vec![id, id / 1000 * 1000],
+        Some(id) => vec![id],
+        None => vec![],
+    }
+}
+
+/// Whether an id is a YGG category or sub-category.
+pub fn is_ygg_category(ygg_id: usize) -> bool {
+    NEWZNAB_MAPPING
+        .iter()
+        .any(|(id, _, subs)| *id == ygg_id || subs.iter().any(|(sub, _)| *sub == ygg_id))
+}
+
+/// Parent category of a YGG sub-category.
+fn ygg_parent(sub_id: usize) -> Option<usize> {
+    NEWZNAB_MAPPING
+        .iter()
+        .find(|(_, _, subs)| subs.iter().any(|(sub, _)| *sub == sub_id))
+        .map(|(id, _, _)| *id)
+}
+
+/// Replaces the sub-categories of a list by their parent when the parent or
+/// another of its sub-categories is listed too, so the parent is searched once
+/// (`[2179, 2184, 2148]` → `[2145, 2148]`).
+pub fn merge_sub_categories(ids: &[usize]) -> Vec<usize> {
+    let mut merged: Vec<usize> = Vec::new();
+    for &id in ids {
+        let id = match ygg_parent(id) {
+            Some(parent)
+                if ids.contains(&parent)
+                    || ids
+                        .iter()
+                        .filter(|&&other| ygg_parent(other) == Some(parent))
+                        .count()
+                        > 1 =>
+            {
+                parent
+            }
+            _ => id,
+        };
+        if !merged.contains(&id) {
+            merged.push(id);
+        }
+    }
+    merged
+}
+
+/// YGG sub-categories matching a Newznab category (`5000` gives every TV
+/// sub-category), or `None` when the id is not a mapped Newznab category.
+pub fn ygg_categories_from_newznab(newznab_id: usize) -> Option<Vec<usize>> {
+    if is_ygg_category(newznab_id) {
+        return None;
+    }
+    let ids: Vec<usize> = NEWZNAB_MAPPING
+        .iter()
+        .flat_map(|(_, _, subs)| subs.iter())
+        .filter(|(_, id)| {
+            *id == newznab_id || (newznab_id.is_multiple_of(1000) && id / 1000 * 1000 == newznab_id)
+        })
+        .map(|(sub_id, _)| *sub_id)
+        .collect();
+    (!ids.is_empty()).then_some(ids)
 }
 
 pub(crate) async fn scrape_categories(
@@ -65,6 +306,7 @@ pub(crate) async fn scrape_categories(
                         id: sub_id.parse::<usize>()?,
                         name,
                         sub_categories: vec![],
+                        options: vec![],
                     });
                 }
             }
@@ -73,6 +315,7 @@ pub(crate) async fn scrape_categories(
                 id: cat_id.parse::<usize>()?,
                 name: main_name,
                 sub_categories: subs,
+                options: vec![],
             });
         }
     }
@@ -90,9 +333,395 @@ pub async fn init_categories(client: &YggClient) -> Result<(), Box<dyn std::erro
     Ok(())
 }
 
+/// Search options of a sub-category, scraped from its YGG search page on first
+/// use and kept for the lifetime of the process.
+pub async fn category_options(
+    client: &YggClient,
+    sub_category: usize,
+) -> Result<Vec<CategoryOption>, Box<dyn std::error::Error>> {
+    if let Some(options) = sub_category_options(sub_category) {
+        return Ok(options);
+    }
+    let category = CATEGORIES_CACHE
+        .get()
+        .and_then(|categories| {
+            categories
+                .iter()
+                .find(|cat| cat.sub_categories.iter().any(|sub| sub.id == sub_category))
+        })
+        .map(|cat| cat.id)
+        .ok_or_else(|| format!("Unknown sub-category: {}", sub_category))?;
+
+    let options = scrape_options(client, category, sub_category).await?;
+    debug!(
+        "Loaded {} search options of sub-category {}",
+        options.len(),
+        sub_category
+    );
+    if let Ok(mut cache) = CATEGORY_OPTIONS.write() {
+        cache.insert(sub_category, options.clone());
+    }
+    Ok(options)
+}
+
+async fn scrape_options(
+    client: &YggClient,
+    category: usize,
+    sub_category: usize,
+) -> Result<Vec<CategoryOption>, Box<dyn std::error::Error>> {
+    let domain = {
+        let domain_lock = DOMAIN.lock()?;
+        domain_lock.clone()
+    };
+
+    let _guard = get_rate_limiter().acquire().await;
+    let url = format!(
+        "https://{}/engine/search?category={}&sub_category={}&do=search",
+        domain, category, sub_category
+    );
+    let response = client.get(&url).await?;
+    if check_session_expired(response.status, &response.url) {
+        return Err("Session expired".into());
+    }
+    parse_options(&response.body)
+}
+
+/// Extracts the `option_*` fields of the advanced search form.
+pub fn parse_options(body: &str) -> Result<Vec<CategoryOption>, Box<dyn std::error::Error>> {
+    let document = Html::parse_document(body);
+    let select_selector = Selector::parse("select[name^=\"option_\"]")?;
+    let option_selector = Selector::parse("option")?;
+
+    let mut options: Vec<CategoryOption> = Vec::new();
+    for select in document.select(&select_selector) {
+        let param = select.value().attr("name").unwrap_or_default().to_string();
+        let name = param
+            .split([':', '['])
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        if options.iter().any(|option| option.name == name) {
+            continue;
+        }
+
+        let label = select
+            .value()
+            .id()
+            .and_then(|id| Selector::parse(&format!("label[for=\"{}\"]", id)).ok())
+            .and_then(|selector| document.select(&selector).next())
+            .map(|label| label.text().collect::<String>().trim().to_string())
+            .filter(|label| !label.is_empty())
+            .unwrap_or_else(|| name.trim_start_matches("option_").to_string());
+
+        let values: Vec<OptionValue> = select
+            .select(&option_selector)
+            .filter_map(|option| {
+                let id = option.value().attr("value")?.trim();
+                let label = option.text().collect::<String>().trim().to_string();
+                (!id.is_empty() && !label.is_empty()).then(|| OptionValue {
+                    id: id.to_string(),
+                    label,
+                })
+            })
+            .collect();
+
+        if !values.is_empty() {
+            options.push(CategoryOption {
+                name,
+                param,
+                label,
+                values,
+            });
+        }
+    }
+    Ok(options)
+}
+
+/// Categories with the search options loaded so far, for `/categories`.
+pub fn with_options(categories: &[Category]) -> Vec<Category> {
+    let Ok(cache) = CATEGORY_OPTIONS.read() else {
+        return categories.to_vec();
+    };
+    let mut categories = categories.to_vec();
+    for sub_category in categories
+        .iter_mut()
+        .flat_map(|category| category.sub_categories.iter_mut())
+    {
+        if let Some(options) = cache.get(&sub_category.id) {
+            sub_category.options = options.clone();
+        }
+    }
+    categories
+}
+
+/// Search options of a sub-category, once loaded.
+pub fn sub_category_options(sub_category: usize) -> Option<Vec<CategoryOption>> {
+    CATEGORY_OPTIONS.read().ok()?.get(&sub_category).cloned()
+}
+
+/// Translates `/search` option filters (`option_langue=multi,vff`) into YGG
+/// `(parameter, value id)` pairs. Values match an id, or words of their label.
+pub fn resolve_options(
+    options: &[CategoryOption],
+    requested: &[(String, String)],
+) -> Result<Vec<(String, String)>, String> {
+    let mut resolved: Vec<(String, String)> = Vec::new();
+    for (name, list) in requested {
+        let option = options
+            .iter()
+            .find(|option| option.name == *name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = options.iter().map(|o| o.name.as_str()).collect();
+                format!(
+                    "Unknown option: {}. Valid options: {}",
+                    name,
+                    names.join(", ")
+                )
+            })?;
+
+        for value in list.split(',').map(str::trim).filter(|v| !v.is_empty()) {
+            let wanted = format!("-{}-", slugify(value));
+            let matches: Vec<&OptionValue> = option
+                .values
+                .iter()
+                .filter(|v| v.id == value || format!("-{}-", slugify(&v.label)).contains(&wanted))
+                .collect();
+            if matches.is_empty() {
+                let choices: Vec<String> = option
+                    .values
+                    .iter()
+                    .map(|v| format!("{} ({})", slugify(&v.label), v.id))
+                    .collect();
+                return Err(format!(
+                    "Unknown value for {}: {}. Valid choices: {}",
+                    name,
+                    value,
+                    choices.join(", ")
+                ));
+            }
+            for v in matches {
+                let pair = (option.param.clone(), v.id.clone());
+                if !resolved.contains(&pair) {
+                    resolved.push(pair);
+                }
+            }
+        }
+    }
+    Ok(resolved)
+}
+
 fn extract_param(url: &str, param: &str) -> Option<String> {
     url.split('&')
         .find(|s| s.contains(param))
         .and_then(|s| s.split('=').nth(1))
         .map(|s| s.to_string())
 }
+
+/// Normalizes a category name for lookups: lowercase, without accents, words joined
+/// by dashes (`Film/Vidéo` → `film-video`, `Série TV` → `serie-tv`).
+pub fn slugify(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.to_lowercase().chars() {
+        let folded = match c {
+            'à' | 'â' | 'ä' | 'á' | 'ã' | 'å' => "a",
+            'ç' => "c",
+            'é' | 'è' | 'ê' | 'ë' => "e",
+            'î' | 'ï' | 'í' | 'ì' => "i",
+            'ô' | 'ö' | 'ó' | 'ò' | 'õ' => "o",
+            'û' | 'ü' | 'ú' | 'ù' => "u",
+            'ÿ' => "y",
+            'ñ' => "n",
+            'œ' => "oe",
+            'æ' => "ae",
+            c if c.is_ascii_alphanumeric() => {
+                slug.push(c);
+                continue;
+            }
+            _ => "-",
+        };
+        if folded == "-" && (slug.is_empty() || slug.ends_with('-')) {
+            continue;
+        }
+        slug.push_str(folded);
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+/// Resolves a category given by id, name or slug (`film`, `serie-tv`,
+/// `film-video/animation`) against the cached categories.
+pub fn resolve_category(value: &str, sub_only: bool) -> Result<usize, String> {
+    let categories = CATEGORIES_CACHE.get().map(Vec::as_slice).unwrap_or(&[]);
+    resolve_category_in(categories, value, sub_only)
+}
+
+/// Resolves a category in the given list. Numeric ids are returned as is; names
+/// match a main category first, then a sub-category. With `sub_only`, only
+/// sub-categories are looked up.
+pub fn resolve_category_in(
+    categories: &[Category],
+    value: &str,
+    sub_only: bool,
+) -> Result<usize, String> {
+    let value = value.trim();
+    if let Ok(id) = value.parse::<usize>() {
+        return Ok(id);
+    }
+
+    let found = match value.split_once('/') {
+        // `parent/sub`, unless the slash is part of the name (`Film/Vidéo`)
+        Some((parent, sub)) if find_by_slug(categories, value, sub_only).is_none() => categories
+            .iter()
+            .filter(|cat| slugify(&cat.name) == slugify(parent))
+            .find_map(|cat| find_by_slug(&cat.sub_categories, sub, false)),
+        _ => find_by_slug(categories, value, sub_only),
+    };
+
+    found.ok_or_else(|| {
+        let choices: Vec<String> = categories
+            .iter()
+            .flat_map(|cat| {
+                let parent = slugify(&cat.name);
+                let subs = cat
+                    .sub_categories
+                    .iter()
+                    .map(move |sub| format!("{}/{}", parent, slugify(&sub.name)));
+                let main = (!sub_only).then(|| slugify(&cat.name));
+                main.into_iter().chain(subs)
+            })
+            .collect();
+        match choices.is_empty() {
+            true => format!("Unknown category: {} (categories are not loaded)", value),
+            false => format!(
+                "Unknown category: {}. Valid choices: {}",
+                value,
+                choices.join(", ")
+            ),
+        }
+    })
+}
+
+fn find_by_slug(categories: &[Category], value: &str, sub_only: bool) -> Option<usize> {
+    let slug = slugify(value);
+    if slug.is_empty() {
+        return None;
+    }
+    let main = categories
+        .iter()
+        .filter(|_| !sub_only)
+        .find(|cat| slugify(&cat.name) == slug);
+    let sub = || {
+        categories
+            .iter()
+            .flat_map(|cat| cat.sub_categories.iter())
+            .find(|sub| slugify(&sub.name) == slug)
+    };
+    main.or_else(sub).map(|cat| cat.id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::categories;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Film/Vidéo"), "film-video");
+        assert_eq!(slugify("Série TV"), "serie-tv");
+        assert_eq!(slugify(" Émission TV "), "emission-tv");
+    }
+
+    #[test]
+    fn test_resolve_category() {
+        let cats = categories();
+        assert_eq!(resolve_category_in(&cats, "2183", false), Ok(2183));
+        assert_eq!(resolve_category_in(&cats, "film", false), Ok(2183));
+        assert_eq!(resolve_category_in(&cats, "Film/Vidéo", false), Ok(2145));
+        assert_eq!(resolve_category_in(&cats, "film-video", false), Ok(2145));
+        assert_eq!(resolve_category_in(&cats, "SERIE-TV", true), Ok(2184));
+        assert_eq!(resolve_category_in(&cats, "série tv", false), Ok(2184));
+        assert_eq!(
+            resolve_category_in(&cats, "film-video/animation", true),
+            Ok(2178)
+        );
+        assert_eq!(resolve_category_in(&cats, "audio", false), Ok(2139));
+        assert!(resolve_category_in(&cats, "audio", true).is_err());
+
+        let err = resolve_category_in(&cats, "jeux", false).unwrap_err();
+        assert!(err.starts_with("Unknown category: jeux. Valid choices: film-video, "));
+        assert!(err.contains("film-video/serie-tv"));
+    }
+
+    #[test]
+    fn test_search_options() {
+        let body = r#"<form>
+            <label for="langue">Langue</label>
+            <select id="langue" name="option_langue:multiple[]" multiple>
+                <option value="1">Anglais</option>
+                <option value="2">Français (VFF)</option>
+                <option value="4">Multi (Français inclus)</option>
+                <option value="8">VFSTFR</option>
+            </select>
+            <select name="option_qualite[]">
+                <option value="">Toutes</option>
+                <option value="21">HDrip 1080 [Rip HD depuis Bluray]</option>
+                <option value="22">WEBrip 1080</option>
+            </select>
+        </form>"#;
+        let options = parse_options(body).unwrap();
+        assert_eq!(options.len(), 2);
+        assert_eq!(options[0].name, "option_langue");
+        assert_eq!(options[0].param, "option_langue:multiple[]");
+        assert_eq!(options[0].label, "Langue");
+        assert_eq!(options[1].label, "qualite");
+        assert_eq!(options[1].values.len(), 2);
+
+        let requested = vec![
+            ("option_langue".to_string(), "multi,vff".to_string()),
+            ("option_qualite".to_string(), "22".to_string()),
+        ];
+        assert_eq!(
+            resolve_options(&options, &requested),
+            Ok(vec![
+                ("option_langue:multiple[]".to_string(), "4".to_string()),
+                ("option_langue:multiple[]".to_string(), "2".to_string()),
+                ("option_qualite[]".to_string(), "22".to_string()),
+            ])
+        );
+
+        let unknown = vec![("option_langue".to_string(), "klingon".to_string())];
+        let err = resolve_options(&options, &unknown).unwrap_err();
+        assert!(
+            err.starts_with("Unknown value for option_langue: klingon. Valid choices: anglais (1)")
+        );
+        let unknown = vec![("option_genre".to_string(), "1".to_string())];
+        assert!(resolve_options(&options, &unknown).is_err());
+    }
+
+    #[test]
+    fn test_newznab_categories() {
+        assert_eq!(newznab_categories(2183), vec![2000]);
+        assert_eq!(newznab_categories(2179), vec![5070, 5000]);
+        assert_eq!(newznab_categories(2145), vec![5000]);
+
+        // Every mapped Newznab category is named in the Torznab caps
+        for (_, id, subs) in NEWZNAB_MAPPING {
+            for id in std::iter::once(id).chain(subs.iter().map(|(_, id)| id)) {
+                assert!(NEWZNAB_NAMES.iter().any(|(named, _)| named == id), "{}", id);
+            }
+        }
+        assert!(newznab_categories(1).is_empty());
+
+        assert_eq!(ygg_categories_from_newznab(2000), Some(vec![2178, 2183]));
+        assert_eq!(ygg_categories_from_newznab(5070), Some(vec![2179]));
+        assert_eq!(
+            ygg_categories_from_newznab(5000),
+            Some(vec![2179, 2181, 2182, 2184, 2185, 2186, 2187])
+        );
+        assert_eq!(ygg_categories_from_newznab(2183), None);
+        assert_eq!(ygg_categories_from_newznab(2040), None);
+
+        assert_eq!(merge_sub_categories(&[2179, 2184, 2148]), vec![2145, 2148]);
+        assert_eq!(merge_sub_categories(&[2184, 2145]), vec![2145]);
+        assert_eq!(merge_sub_categories(&[2184]), vec![2184]);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::download_clients::DownloadClient;
 use log::LevelFilter;
 use serde::{Deserialize, Serialize};
 
@@ -89,6 +90,65 @@ fn load_config_from_env() -> Result<Config, std::io::Error> {
     let ygg_domain = std::env::var("YGG_DOMAIN").ok();
     let flaresolverr_url = std::env::var("FLARESOLVERR_URL").ok();
 
+    let rss_api_key = std::env::var("RSS_API_KEY").ok();
+
+    let search_cache_ttl = std::env::var("SEARCH_CACHE_TTL")
+        .ok()
+        .map(|s| s.parse::<u64>())
+        .transpose()
+        .map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "SEARCH_CACHE_TTL must be a number of seconds",
+            )
+        })?;
+    let search_cache_size = std::env::var("SEARCH_CACHE_SIZE")
+        .ok()
+        .map(|s| s.parse::<usize>())
+        .transpose()
+        .map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "SEARCH_CACHE_SIZE must be a valid number",
+            )
+        })?;
+
+    let torrent_cache_dir = std::env::var("TORRENT_CACHE_DIR").ok();
+    let torrent_cache_size = std::env::var("TORRENT_CACHE_SIZE")
+        .ok()
+        .map(|s| s.parse::<u64>())
+        .transpose()
+        .map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "TORRENT_CACHE_SIZE must be a number of MB",
+            )
+        })?;
+    let torrent_cache_max_age = std::env::var("TORRENT_CACHE_MAX_AGE")
+        .ok()
+        .map(|s| s.parse::<u64>())
+        .transpose()
+        .map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "TORRENT_CACHE_MAX_AGE must be a number of seconds",
+            )
+        })?;
+
+    let download_clients = std::env::var("DOWNLOAD_CLIENTS")
+        .ok()
+        .map(|s| serde_json::from_str::<Vec<DownloadClient>>(&s))
+        .transpose()
+        .map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "DOWNLOAD_CLIENTS must be a JSON list of download clients: {}",
+                    e
+                ),
+            )
+        })?;
+
     Ok(Config {
         username,
         password,
@@ -99,6 +159,13 @@ fn load_config_from_env() -> Result<Config, std::io::Error> {
         ygg_domain,
         turbo_enabled,
         flaresolverr_url,
+        search_cache_ttl,
+        search_cache_size,
+        torrent_cache_dir,
+        torrent_cache_size,
+        torrent_cache_max_age,
+        download_clients,
+        rss_api_key,
     })
 }
 
@@ -114,6 +181,13 @@ pub struct Config {
     pub ygg_domain: Option<String>,
     pub turbo_enabled: Option<bool>,
     pub flaresolverr_url: Option<String>,
+    pub search_cache_ttl: Option<u64>,
+    pub search_cache_size: Option<usize>,
+    pub torrent_cache_dir: Option<String>,
+    pub torrent_cache_size: Option<u64>,
+    pub torrent_cache_max_age: Option<u64>,
+    pub download_clients: Option<Vec<DownloadClient>>,
+    pub rss_api_key: Option<String>,
 }
 
 impl Default for Config {
@@ -128,6 +202,13 @@ impl Default for Config {
             ygg_domain: Some("www.yggtorrent.org".to_string()),
             turbo_enabled: None,
             flaresolverr_url: None,
+            search_cache_ttl: None,
+            search_cache_size: None,
+            torrent_cache_dir: None,
+            torrent_cache_size: None,
+            torrent_cache_max_age: None,
+            download_clients: None,
+            rss_api_key: None,
         }
     }
 }
@@ -1,3 +1,4 @@
+use crate::explain::{self, TmdbRecord};
 use wreq::Client;
 
 pub async fn get_account_username(token: &String) -> Result<String, Box<dyn std::error::Error>> {
@@ -27,6 +28,37 @@ pub async fn get_account_username(token: &String) -> Result<String, Box<dyn std:
 pub enum DbQueryType {
     TMDB,
     IMDB,
+    TVDB,
+}
+
+/// Kind of TMDB record to resolve, picked from the search mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaType {
+    Movie,
+    Tv,
+}
+
+impl MediaType {
+    fn path(&self) -> &'static str {
+        match self {
+            MediaType::Movie => "movie",
+            MediaType::Tv => "tv",
+        }
+    }
+
+    fn results_key(&self) -> &str {
+        match self {
+            MediaType::Movie => "movie_results",
+            MediaType::Tv => "tv_results",
+        }
+    }
+
+    fn other(&self) -> MediaType {
+        match self {
+            MediaType::Movie => MediaType::Tv,
+            MediaType::Tv => MediaType::Movie,
+        }
+    }
 }
 
 const ALLOWED_CHARS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789-_. ";
@@ -97,23 +129,13 @@ fn fix_title(title: &str) -> String {
         .to_string()
 }
 
-pub async fn get_queries(
-    id: String,
+async fn tmdb_get(
+    client: &Client,
+    url: &str,
     token: &String,
-    db_type: DbQueryType,
-) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-    debug!("Fetching TMDB titles for ID: {}", id);
-    let client = Client::new();
-    //let url = format!("https://api.themoviedb.org/3/movie/{}", id);
-    let url = match db_type {
-        DbQueryType::TMDB => format!("https://api.themoviedb.org/3/movie/{}", id),
-        DbQueryType::IMDB => format!(
-            "https://api.themoviedb.org/3/find/{}?external_source=imdb_id",
-            id
-        ),
-    };
+) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
     let response = client
-        .get(&url)
+        .get(url)
         .header("Authorization", format!("Bearer {}", token))
         .header("accept", "application/json")
         .send()
@@ -122,14 +144,48 @@ pub async fn get_queries(
     if !response.status().is_success() {
         // 404
         if response.status().as_u16() == 404 {
-            return Err("TMDB movie not found".into());
+            return Err("TMDB record not found".into());
         }
 
-        return Err(format!("Failed to fetch TMDB movie info: {}", response.status()).into());
+        return Err(format!("Failed to fetch TMDB info: {}", response.status()).into());
     }
 
     let body = response.text().await?;
-    let json: serde_json::Value = serde_json::from_str(&body)?;
+    Ok(serde_json::from_str(&body)?)
+}
+
+/// Picks the record matching `media_type` in a `/find` response, falling back
+/// to the other kind when the external id belongs to it.
+fn pick_find_result(
+    json: &serde_json::Value,
+    media_type: MediaType,
+) -> Option<(MediaType, serde_json::Value)> {
+    [media_type, media_type.other()]
+        .into_iter()
+        .find_map(|media_type| {
+            json.get(media_type.results_key())
+                .and_then(|r| r.as_array())
+                .and_then(|r| r.first())
+                .map(|record| (media_type, record.clone()))
+        })
+}
+
+fn format_query(title: &str, year: u32, media_type: MediaType) -> String {
+    match media_type {
+        MediaType::Movie => format!("{} {}", title, year),
+        MediaType::Tv => title.to_string(),
+    }
+}
+
+/// Builds the base queries (original title and localized title) of a TMDB record.
+fn record_queries(
+    json: &serde_json::Value,
+    media_type: MediaType,
+) -> Result<(u64, u32, Vec<String>), Box<dyn std::error::Error>> {
+    let (title_key, original_title_key, date_key) = match media_type {
+        MediaType::Movie => ("title", "original_title", "release_date"),
+        MediaType::Tv => ("name", "original_name", "first_air_date"),
+    };
 
     let id = json
         .get("id")
@@ -137,20 +193,20 @@ pub async fn get_queries(
         .ok_or("ID not found in TMDB response")?;
 
     let year = json
-        .get("release_date")
+        .get(date_key)
         .and_then(|rd| rd.as_str())
         .and_then(|date_str| date_str.split('-').next())
         .and_then(|year_str| year_str.parse::<u32>().ok())
         .unwrap_or(0);
 
     let original_title = json
-        .get("original_title")
+        .get(original_title_key)
         .and_then(|ot| ot.as_str())
         .ok_or("Original title not found in TMDB response")?
         .to_string();
 
     let title = json
-        .get("title")
+        .get(title_key)
         .and_then(|t| t.as_str())
         .ok_or("Title not found in TMDB response")?
         .to_string();
@@ -159,36 +215,30 @@ pub async fn get_queries(
     if original_title != title {
         let original_title = fix_title(&original_title);
         if !original_title.is_empty() {
-            titles.push(format!("{} {}", original_title, year));
+            titles.push(format_query(&original_title, year, media_type));
         }
     }
     let title = fix_title(&title);
     if !title.is_empty() {
-        titles.push(format!("{} {}", title, year));
+        titles.push(format_query(&title, year, media_type));
     }
 
-    let alt_url = format!(
-        "https://api.themoviedb.org/3/movie/{}/alternative_titles",
-        id
-    );
-    let alt_response = client
-        .get(&alt_url)
-        .header("Authorization", format!("Bearer {}", token))
-        .header("accept", "application/json")
-        .send()
-        .await?;
+    Ok((id, year, titles))
+}
 
-    if !alt_response.status().is_success() {
-        return Err(format!(
-            "Failed to fetch TMDB alternative titles: {}",
-            alt_response.status()
-        )
-        .into());
-    }
+/// Appends the FR/US/GB alternative titles of a TMDB record to `titles`.
+fn add_alternative_titles(
+    json: &serde_json::Value,
+    media_type: MediaType,
+    year: u32,
+    titles: &mut Vec<String>,
+) {
+    let list_key = match media_type {
+        MediaType::Movie => "titles",
+        MediaType::Tv => "results",
+    };
 
-    let body = alt_response.text().await?;
-    let json: serde_json::Value = serde_json::from_str(&body)?;
-    if let Some(titles_array) = json.get("titles").and_then(|t| t.as_array()) {
+    if let Some(titles_array) = json.get(list_key).and_then(|t| t.as_array()) {
         for title_entry in titles_array {
             if let Some(iso_3166_1) = title_entry.get("iso_3166_1").and_then(|c| c.as_str()) {
                 if iso_3166_1 == "FR"
@@ -197,18 +247,19 @@ pub async fn get_queries(
                     || iso_3166_1 == "EN"
                 {
                     if let Some(title) = title_entry.get("title").and_then(|t| t.as_str()) {
-                        if !titles.contains(&title.to_string()) {
-                            let title = match title.ends_with("1") {
-                                true => title.trim_end_matches("1").trim(),
-                                false => title,
-                            };
-                            let title = match title.starts_with("1") {
-                                true => title.trim_start_matches("1").trim(),
-                                false => title,
-                            };
-                            let title = fix_title(title);
-                            if !title.is_empty() {
-                                titles.push(format!("{} {}", title, year));
+                        let title = match title.ends_with("1") {
+                            true => title.trim_end_matches("1").trim(),
+                            false => title,
+                        };
+                        let title = match title.starts_with("1") {
+                            true => title.trim_start_matches("1").trim(),
+                            false => title,
+                        };
+                        let title = fix_title(title);
+                        if !title.is_empty() {
+                            let query = format_query(&title, year, media_type);
+                            if !titles.contains(&query) {
+                                titles.push(query);
                             }
                         }
                     }
@@ -216,6 +267,117 @@ pub async fn get_queries(
             }
         }
     }
+}
+
+pub async fn get_queries(
+    id: String,
+    token: &String,
+    db_type: DbQueryType,
+    media_type: MediaType,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    debug!("Fetching TMDB titles for ID: {} ({:?})", id, media_type);
+    let client = Client::new();
+
+    let (media_type, record) = match db_type {
+        DbQueryType::TMDB => {
+            let url = format!("https://api.themoviedb.org/3/{}/{}", media_type.path(), id);
+            (media_type, tmdb_get(&client, &url, token).await?)
+        }
+        DbQueryType::IMDB | DbQueryType::TVDB => {
+            let (source, media_type) = match db_type {
+                DbQueryType::TVDB => ("tvdb_id", MediaType::Tv),
+                _ => ("imdb_id", media_type),
+            };
+            let url = format!(
+                "https://api.themoviedb.org/3/find/{}?external_source={}",
+                id, source
+            );
+            let json = tmdb_get(&client, &url, token).await?;
+            pick_find_result(&json, media_type).ok_or("No TMDB record found for this ID")?
+        }
+    };
+
+    let (id, year, mut titles) = record_queries(&record, media_type)?;
+    explain::record(|explain| {
+        let text = |keys: [&str; 2]| {
+            keys.iter()
+                .find_map(|key| record.get(*key).and_then(|v| v.as_str()))
+                .unwrap_or("")
+                .to_string()
+        };
+        explain.record = Some(TmdbRecord {
+            media_type: media_type.path(),
+            tmdb_id: id,
+            year,
+            title: text(["title", "name"]),
+            original_title: text(["original_title", "original_name"]),
+        });
+    });
+
+    let alt_url = format!(
+        "https://api.themoviedb.org/3/{}/{}/alternative_titles",
+        media_type.path(),
+        id
+    );
+    let json = tmdb_get(&client, &alt_url, token)
+        .await
+        .map_err(|e| format!("Failed to fetch TMDB alternative titles: {}", e))?;
+    add_alternative_titles(&json, media_type, year, &mut titles);
 
     Ok(titles)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_pick_find_result() {
+        let json = json!({
+            "movie_results": [],
+            "tv_results": [{ "id": 1396, "name": "Breaking Bad" }],
+        });
+        let (media_type, record) = pick_find_result(&json, MediaType::Movie).unwrap();
+        assert_eq!(media_type, MediaType::Tv);
+        assert_eq!(record["id"], 1396);
+
+        let json = json!({ "movie_results": [], "tv_results": [] });
+        assert!(pick_find_result(&json, MediaType::Tv).is_none());
+    }
+
+    #[test]
+    fn test_record_queries() {
+        let movie = json!({
+            "id": 277834,
+            "title": "Vaiana, la légende du bout du monde",
+            "original_title": "Moana",
+            "release_date": "2016-11-23",
+        });
+        let (id, year, titles) = record_queries(&movie, MediaType::Movie).unwrap();
+        assert_eq!((id, year), (277834, 2016));
+        assert_eq!(
+            titles,
+            vec!["Moana 2016", "Vaiana  la legende du bout du monde 2016"]
+        );
+
+        let show = json!({
+            "id": 1396,
+            "name": "Breaking Bad",
+            "original_name": "Breaking Bad",
+            "first_air_date": "2008-01-20",
+        });
+        let (_, year, mut titles) = record_queries(&show, MediaType::Tv).unwrap();
+        assert_eq!(titles, vec!["Breaking Bad"]);
+
+        let alternative_titles = json!({
+            "results": [
+                { "iso_3166_1": "FR", "title": "Breaking Bad" },
+                { "iso_3166_1": "US", "title": "Breaking Bad: Le chimiste" },
+                { "iso_3166_1": "DE", "title": "Breaking Bad (DE)" },
+            ]
+        });
+        add_alternative_titles(&alternative_titles, MediaType::Tv, year, &mut titles);
+        assert_eq!(titles, vec!["Breaking Bad", "Breaking Bad Le chimiste"]);
+    }
+}
@@ -0,0 +1,540 @@
+use crate::categories::{CATEGORIES_CACHE, Category, resolve_category_in};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use wreq::Client;
+use wreq::multipart::{Form, Part};
+
+/// Timeouts of the WebUI requests, so an unreachable client fails the send
+/// instead of holding it open.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClientKind {
+    QBittorrent,
+    Transmission,
+    Deluge,
+}
+
+/// Download client profile of the `download_clients` option.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadClient {
+    pub name: String,
+    pub kind: ClientKind,
+    /// WebUI url, like `http://localhost:8080`.
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Category (qBittorrent) or label (Transmission, Deluge) of the torrents.
+    pub category: Option<String>,
+    pub save_path: Option<String>,
+    /// Labels by YGG category (id, name or slug), used instead of `category`. A
+    /// main category also applies to its sub-categories.
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+}
+
+impl DownloadClient {
+    /// Label of a torrent of the given YGG category.
+    pub fn label(&self, category: Option<usize>) -> Option<String> {
+        let categories = CATEGORIES_CACHE.get().map(Vec::as_slice).unwrap_or(&[]);
+        self.label_in(categories, category)
+    }
+
+    fn label_in(&self, categories: &[Category], category: Option<usize>) -> Option<String> {
+        if let Some(category) = category {
+            let parent = categories
+                .iter()
+                .find(|cat| cat.sub_categories.iter().any(|sub| sub.id == category))
+                .map(|cat| cat.id);
+            let labels: Vec<(usize, &String)> = self
+                .labels
+                .iter()
+                .filter_map(|(key, label)| {
+                    let id = resolve_category_in(categories, key, false).ok()?;
+                    Some((id, label))
+                })
+                .collect();
+            let found = labels
+                .iter()
+                .find(|(id, _)| *id == category)
+                .or_else(|| labels.iter().find(|(id, _)| Some(*id) == parent));
+            if let Some((_, label)) = found {
+                return Some(label.to_string());
+            }
+        }
+        self.category.clone()
+    }
+
+    fn endpoint(&self, path: &str) -> String {
+        format!("{}{}", self.url.trim_end_matches('/'), path)
+    }
+
+    /// Uploads a .torrent file through the client WebUI API.
+    pub async fn send(
+        &self,
+        filename: &str,
+        bytes: &[u8],
+        label: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let client = Client::builder()
+            .cookie_store(true)
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+        debug!("Sending {} to {} ({:?})", filename, self.name, self.kind);
+        match self.kind {
+            ClientKind::QBittorrent => self.qbittorrent_add(&client, filename, bytes, label).await,
+            ClientKind::Transmission => self.transmission_add(&client, bytes, label).await,
+            ClientKind::Deluge => self.deluge_add(&client, filename, bytes, label).await,
+        }
+    }
+
+    async fn qbittorrent_add(
+        &self,
+        client: &Client,
+        filename: &str,
+        bytes: &[u8],
+        label: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // The SID cookie is kept by the cookie store
+        if let Some(username) = &self.username {
+            let payload = [
+                ("username", username.as_str()),
+                ("password", self.password.as_deref().unwrap_or("")),
+            ];
+            let response = client
+                .post(self.endpoint("/api/v2/auth/login"))
+                .header("Referer", self.url.as_str())
+                .form(&payload)
+                .send()
+                .await?;
+            let status = response.status();
+            let body = response.text().await?;
+            if !status.is_success() || body.trim() != "Ok." {
+                return Err(format!("qBittorrent login failed: {} {}", status, body).into());
+            }
+        }
+
+        let file = Part::bytes(bytes.to_vec())
+            .file_name(filename.to_string())
+            .mime_str("application/x-bittorrent")?;
+        let mut form = Form::new().part("torrents", file);
+        if let Some(label) = label {
+            form = form.text("category", label.to_string());
+        }
+        if let Some(save_path) = &self.save_path {
+            form = form.text("savepath", save_path.clone());
+        }
+
+        let response = client
+            .post(self.endpoint("/api/v2/torrents/add"))
+            .header("Referer", self.url.as_str())
+            .multipart(form)
+            .send()
+            .await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() || body.trim() == "Fails." {
+            return Err(format!("qBittorrent refused the torrent: {} {}", status, body).into());
+        }
+        Ok(())
+    }
+
+    async fn transmission_add(
+        &self,
+        client: &Client,
+        bytes: &[u8],
+        label: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let url = match self.url.trim_end_matches('/').ends_with("/rpc") {
+            true => self.url.clone(),
+            false => self.endpoint("/transmission/rpc"),
+        };
+        let mut arguments = json!({ "metainfo": BASE64.encode(bytes) });
+        if let Some(save_path) = &self.save_path {
+            arguments["download-dir"] = json!(save_path);
+        }
+        if let Some(label) = label {
+            arguments["labels"] = json!([label]);
+        }
+        let request = json!({ "method": "torrent-add", "arguments": arguments });
+
+        // The first call is answered 409 with the session id to use
+        let mut session_id: Option<String> = None;
+        for _ in 0..2 {
+            let mut builder = client.post(url.as_str()).json(&request);
+            if let Some(session_id) = &session_id {
+                builder = builder.header("X-Transmission-Session-Id", session_id.as_str());
+            }
+            if let Some(username) = &self.username {
+                builder = builder.basic_auth(username, self.password.as_deref());
+            }
+            let response = builder.send().await?;
+
+            if response.status().as_u16() == 409 {
+                session_id = response
+                    .headers()
+                    .get("X-Transmission-Session-Id")
+                    .and_then(|id| id.to_str().ok())
+                    .map(|id| id.to_string());
+                if session_id.is_none() {
+                    return Err("Transmission session id not found".into());
+                }
+                continue;
+            }
+            if !response.status().is_success() {
+                return Err(format!("Transmission request failed: {}", response.status()).into());
+            }
+
+            let json: Value = serde_json::from_str(&response.text().await?)?;
+            return match json.get("result").and_then(|r| r.as_str()) {
+                Some("success") => Ok(()),
+                result => Err(format!(
+                    "Transmission refused the torrent: {}",
+                    result.unwrap_or("")
+                )
+                .into()),
+            };
+        }
+        Err("Transmission rejected the session id".into())
+    }
+
+    async fn deluge_add(
+        &self,
+        client: &Client,
+        filename: &str,
+        bytes: &[u8],
+        label: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let url = self.endpoint("/json");
+        let call = |method: &'static str, params: Value| deluge_call(client, &url, method, params);
+
+        let password = self.password.as_deref().unwrap_or("");
+        if call("auth.login", json!([password])).await? != json!(true) {
+            return Err("Deluge login failed".into());
+        }
+
+        // The WebUI must be connected to a daemon, the first one is used
+        if call("web.connected", json!([])).await? != json!(true) {
+            let hosts = call("web.get_hosts", json!([])).await?;
+            let host = hosts
+                .get(0)
+                .and_then(|host| host.get(0))
+                .and_then(|id| id.as_str())
+                .ok_or("No Deluge daemon configured")?
+                .to_string();
+            call("web.connect", json!([host])).await?;
+        }
+
+        let mut options = json!({});
+        if let Some(save_path) = &self.save_path {
+            options["download_location"] = json!(save_path);
+        }
+        let hash = call(
+            "core.add_torrent_file",
+            json!([filename, BASE64.encode(bytes), options]),
+        )
+        .await?;
+
+        // Labels need the Label plugin, and are lowercase
+        if let (Some(label), Some(hash)) = (label, hash.as_str()) {
+            let label = label.to_lowercase();
+            if let Err(e) = call("label.add", json!([label])).await {
+                debug!("Deluge label.add: {}", e);
+            }
+            call("label.set_torrent", json!([hash, label])).await?;
+        }
+        Ok(())
+    }
+}
+
+async fn deluge_call(
+    client: &Client,
+    url: &str,
+    method: &str,
+    params: Value,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    let request = json!({ "method": method, "params": params, "id": 1 });
+    let response = client.post(url).json(&request).send().await?;
+    if !response.status().is_success() {
+        return Err(format!("Deluge {} failed: {}", method, response.status()).into());
+    }
+    let json: Value = serde_json::from_str(&response.text().await?)?;
+    if let Some(error) = json.get("error").filter(|error| !error.is_null()) {
+        let message = error.get("message").and_then(|m| m.as_str()).unwrap_or("");
+        return Err(format!("Deluge {} failed: {}", method, message).into());
+    }
+    Ok(json.get("result").cloned().unwrap_or(Value::Null))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    fn profile(kind: ClientKind, url: &str) -> DownloadClient {
+        DownloadClient {
+            name: "test".to_string(),
+            kind,
+            url: url.to_string(),
+            username: Some("admin".to_string()),
+            password: Some("secret".to_string()),
+            category: Some("ygege".to_string()),
+            save_path: Some("/downloads".to_string()),
+            labels: BTreeMap::from([
+                ("film".to_string(), "films".to_string()),
+                ("serie-tv".to_string(), "series".to_string()),
+            ]),
+        }
+    }
+
+    fn category(id: usize, name: &str, sub_categories: Vec<Category>) -> Category {
+        Category {
+            id,
+            name: name.to_string(),
+            sub_categories,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_label() {
+        let categories = vec![
+            category(
+                2145,
+                "Film/Vidéo",
+                vec![
+                    category(2183, "Film", vec![]),
+                    category(2184, "Série TV", vec![]),
+                ],
+            ),
+            category(2139, "Audio", vec![category(2148, "Musique", vec![])]),
+        ];
+        let mut client = profile(ClientKind::QBittorrent, "http://localhost:8080");
+        assert_eq!(
+            client.label_in(&categories, Some(2183)).as_deref(),
+            Some("films")
+        );
+        assert_eq!(
+            client.label_in(&categories, Some(2184)).as_deref(),
+            Some("series")
+        );
+        assert_eq!(
+            client.label_in(&categories, Some(2148)).as_deref(),
+            Some("ygege")
+        );
+        assert_eq!(client.label_in(&categories, None).as_deref(), Some("ygege"));
+
+        client
+            .labels
+            .insert("audio".to_string(), "music".to_string());
+        assert_eq!(
+            client.label_in(&categories, Some(2148)).as_deref(),
+            Some("music")
+        );
+    }
+
+    #[derive(Debug, Clone)]
+    struct MockRequest {
+        path: String,
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+    }
+
+    impl MockRequest {
+        fn header(&self, name: &str) -> Option<&str> {
+            self.headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        }
+
+        fn json(&self) -> Value {
+            serde_json::from_slice(&self.body).unwrap()
+        }
+    }
+
+    type MockResponse = (u16, Vec<(&'static str, String)>, String);
+
+    /// Local mock of a client API: answers each request with `handler`, and
+    /// records the requests.
+    fn mock_server(
+        handler: impl Fn(&MockRequest) -> MockResponse + Send + 'static,
+    ) -> (String, Arc<Mutex<Vec<MockRequest>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    continue;
+                };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let path = line.split(' ').nth(1).unwrap_or("").to_string();
+
+                let mut headers = Vec::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    match line.trim_end().split_once(':') {
+                        Some((key, value)) => {
+                            headers.push((key.trim().to_string(), value.trim().to_string()))
+                        }
+                        None => break,
+                    }
+                }
+                let mut request = MockRequest {
+                    path,
+                    headers,
+                    body: Vec::new(),
+                };
+                match request.header("Content-Length") {
+                    Some(length) => {
+                        request.body = vec![0; length.parse().unwrap()];
+                        reader.read_exact(&mut request.body).unwrap();
+                    }
+                    None if request.header("Transfer-Encoding") == Some("chunked") => loop {
+                        let mut size = String::new();
+                        reader.read_line(&mut size).unwrap();
+                        let size = usize::from_str_radix(size.trim(), 16).unwrap();
+                        let mut chunk = vec![0; size + 2];
+                        reader.read_exact(&mut chunk).unwrap();
+                        if size == 0 {
+                            break;
+                        }
+                        request.body.extend_from_slice(&chunk[..size]);
+                    },
+                    None => {}
+                }
+
+                let (status, headers, body) = handler(&request);
+                recorded.lock().unwrap().push(request);
+                let mut response = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n",
+                    status,
+                    body.len()
+                );
+                for (key, value) in headers {
+                    response.push_str(&format!("{}: {}\r\n", key, value));
+                }
+                response.push_str("\r\n");
+                response.push_str(&body);
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        (url, requests)
+    }
+
+    const TORRENT: &[u8] = b"d4:infod4:name4:test12:piece lengthi16384e6:pieces0:6:lengthi1eee";
+
+    #[tokio::test]
+    async fn test_send_qbittorrent() {
+        let (url, requests) = mock_server(|request| match request.path.as_str() {
+            "/api/v2/auth/login" => (200, vec![("Set-Cookie", "SID=abc".into())], "Ok.".into()),
+            "/api/v2/torrents/add" => match request.header("Cookie") {
+                Some(cookie) if cookie.contains("SID=abc") => (200, vec![], "Ok.".into()),
+                _ => (403, vec![], "Forbidden".into()),
+            },
+            _ => (404, vec![], String::new()),
+        });
+        let client = profile(ClientKind::QBittorrent, &url);
+        client
+            .send("1234.torrent", TORRENT, Some("films"))
+            .await
+            .unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        let login = String::from_utf8_lossy(&requests[0].body);
+        assert_eq!(login, "username=admin&password=secret");
+        let add = String::from_utf8_lossy(&requests[1].body);
+        assert!(add.contains("filename=\"1234.torrent\""));
+        assert!(add.contains("name=\"category\"\r\n\r\nfilms"));
+        assert!(add.contains("name=\"savepath\"\r\n\r\n/downloads"));
+    }
+
+    #[tokio::test]
+    async fn test_send_transmission() {
+        let (url, requests) =
+            mock_server(
+                |request| match request.header("X-Transmission-Session-Id") {
+                    Some("session") => {
+                        (200, vec![], r#"{"result":"success","arguments":{}}"#.into())
+                    }
+                    _ => (
+                        409,
+                        vec![("X-Transmission-Session-Id", "session".into())],
+                        String::new(),
+                    ),
+                },
+            );
+        let client = profile(ClientKind::Transmission, &url);
+        client
+            .send("1234.torrent", TORRENT, Some("films"))
+            .await
+            .unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].path, "/transmission/rpc");
+        assert!(requests[1].header("Authorization").is_some());
+        let json = requests[1].json();
+        assert_eq!(json["method"], "torrent-add");
+        assert_eq!(json["arguments"]["metainfo"], BASE64.encode(TORRENT));
+        assert_eq!(json["arguments"]["download-dir"], "/downloads");
+        assert_eq!(json["arguments"]["labels"], json!(["films"]));
+    }
+
+    #[tokio::test]
+    async fn test_send_deluge() {
+        let (url, requests) = mock_server(|request| {
+            let json = request.json();
+            let (result, error) = match json["method"].as_str().unwrap() {
+                "auth.login" => (json!(json["params"][0] == "secret"), Value::Null),
+                "web.connected" => (json!(true), Value::Null),
+                "core.add_torrent_file" => (json!("0123abcd"), Value::Null),
+                "label.add" => (Value::Null, json!({ "message": "Label already exists" })),
+                _ => (Value::Null, Value::Null),
+            };
+            let body = json!({ "result": result, "error": error, "id": json["id"] });
+            (200, vec![], body.to_string())
+        });
+        let client = profile(ClientKind::Deluge, &url);
+        client
+            .send("1234.torrent", TORRENT, Some("Films"))
+            .await
+            .unwrap();
+
+        let requests = requests.lock().unwrap();
+        let calls: Vec<Value> = requests.iter().map(|r| r.json()).collect();
+        let methods: Vec<&str> = calls
+            .iter()
+            .map(|c| c["method"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            methods,
+            vec![
+                "auth.login",
+                "web.connected",
+                "core.add_torrent_file",
+                "label.add",
+                "label.set_torrent"
+            ]
+        );
+        assert_eq!(calls[2]["params"][1], BASE64.encode(TORRENT));
+        assert_eq!(calls[2]["params"][2]["download_location"], "/downloads");
+        assert_eq!(calls[4]["params"], json!(["0123abcd", "films"]));
+    }
+}
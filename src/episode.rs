@@ -0,0 +1,203 @@
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Season/episode requested by a TV search (`season` and `ep` parameters).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EpisodeQuery {
+    pub season: u32,
+    pub episode: Option<u32>,
+}
+
+/// Season and episode markers found in a release name.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct SeasonMarkers {
+    /// (season, first episode, last episode)
+    episodes: Vec<(u32, u32, u32)>,
+    /// (first season, last season)
+    seasons: Vec<(u32, u32)>,
+    complete: bool,
+}
+
+fn episode_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"\bs(\d{1,2}) ?e(\d{1,3})(?:(?: ?- ?e?|e)(\d{1,3}))*\b|\b(\d{1,2})x(\d{2,3})\b")
+            .unwrap()
+    })
+}
+
+fn season_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"\bs(\d{1,2})(?: ?(?:-|à|a|to) ?s?(\d{1,2}))?\b|\b(?:saisons?|seasons?) ?(\d{1,2})(?: ?(?:-|à|a|to|et|&) ?(\d{1,2}))?\b")
+            .unwrap()
+    })
+}
+
+fn spelled_episode_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\b(?:episodes?|épisodes?|ep) ?(\d{1,3})\b").unwrap())
+}
+
+fn complete_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\b(?:int[ée]grale|integral|complete|compl[eè]te)\b").unwrap())
+}
+
+impl EpisodeQuery {
+    pub fn new(season: Option<u32>, episode: Option<u32>) -> Option<Self> {
+        season.map(|season| EpisodeQuery { season, episode })
+    }
+
+    /// Builds the query variants YGG uploaders use for a season or an episode.
+    pub fn queries(&self, name: &str) -> Vec<String> {
+        let mut queries = Vec::new();
+        if let Some(episode) = self.episode {
+            queries.push(format!("{} S{:02}E{:02}", name, self.season, episode));
+        }
+        queries.push(format!("{} S{:02}", name, self.season));
+        queries.push(format!("{} Saison {}", name, self.season));
+        queries.push(format!("{} Intégrale", name));
+        queries
+    }
+
+    /// Tells whether a release name is the requested episode, or a season pack
+    /// or complete series containing it.
+    pub fn matches(&self, release_name: &str) -> bool {
+        let markers = SeasonMarkers::parse(release_name);
+
+        let episode_match = markers.episodes.iter().any(|&(season, first, last)| {
+            season == self.season && self.episode.is_none_or(|ep| first <= ep && ep <= last)
+        });
+        if episode_match {
+            return true;
+        }
+        if !markers.episodes.is_empty() {
+            return false;
+        }
+
+        let season_match = markers
+            .seasons
+            .iter()
+            .any(|&(first, last)| first <= self.season && self.season <= last);
+        if season_match {
+            return true;
+        }
+
+        markers.complete && markers.seasons.is_empty()
+    }
+}
+
+/// Season and episode of a release name, if any (the episode only for single episodes).
+pub fn season_episode(release_name: &str) -> (Option<u32>, Option<u32>) {
+    let markers = SeasonMarkers::parse(release_name);
+    match (markers.episodes.first(), markers.seasons.first()) {
+        (Some(&(season, first, last)), _) => (Some(season), Some(first).filter(|_| first == last)),
+        (None, Some(&(first, _))) => (Some(first), None),
+        (None, None) => (None, None),
+    }
+}
+
+impl SeasonMarkers {
+    fn parse(release_name: &str) -> Self {
+        let name = release_name.to_lowercase().replace(['.', '_'], " ");
+        let mut markers = SeasonMarkers::default();
+
+        for caps in episode_regex().captures_iter(&name) {
+            let numbers: Vec<Option<u32>> = (1..=5)
+                .map(|i| caps.get(i).and_then(|m| m.as_str().parse().ok()))
+                .collect();
+            match numbers.as_slice() {
+                [Some(season), Some(first), last, _, _] => {
+                    let last = last.unwrap_or(*first).max(*first);
+                    markers.episodes.push((*season, *first, last));
+                }
+                [_, _, _, Some(season), Some(episode)] => {
+                    markers.episodes.push((*season, *episode, *episode));
+                }
+                _ => {}
+            }
+        }
+
+        for caps in season_regex().captures_iter(&name) {
+            let first = caps.get(1).or(caps.get(3));
+            let last = caps.get(2).or(caps.get(4));
+            if let Some(first) = first.and_then(|m| m.as_str().parse::<u32>().ok()) {
+                let last = last
+                    .and_then(|m| m.as_str().parse::<u32>().ok())
+                    .unwrap_or(first)
+                    .max(first);
+                markers.seasons.push((first, last));
+            }
+        }
+
+        // "Saison 2 Episode 5" style names
+        if markers.episodes.is_empty() {
+            let single_season = markers
+                .seasons
+                .first()
+                .filter(|(first, last)| first == last);
+            let spelled_episode = spelled_episode_regex()
+                .captures(&name)
+                .and_then(|caps| caps.get(1))
+                .and_then(|m| m.as_str().parse::<u32>().ok());
+            if let (Some(&(season, _)), Some(episode)) = (single_season, spelled_episode) {
+                markers.episodes.push((season, episode, episode));
+            }
+        }
+
+        markers.complete = complete_regex().is_match(&name);
+        markers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queries() {
+        let query = EpisodeQuery::new(Some(2), Some(5)).unwrap();
+        assert_eq!(
+            query.queries("Breaking Bad"),
+            vec![
+                "Breaking Bad S02E05",
+                "Breaking Bad S02",
+                "Breaking Bad Saison 2",
+                "Breaking Bad Intégrale"
+            ]
+        );
+        let query = EpisodeQuery::new(Some(2), None).unwrap();
+        assert_eq!(query.queries("Breaking Bad").len(), 3);
+        assert_eq!(EpisodeQuery::new(None, Some(5)), None);
+    }
+
+    #[test]
+    fn test_episode_matches() {
+        let query = EpisodeQuery::new(Some(2), Some(5)).unwrap();
+        assert!(query.matches("Breaking.Bad.S02E05.MULTi.1080p.WEB-DL.x264"));
+        assert!(query.matches("Breaking Bad S02E04E05E06 FRENCH HDTV"));
+        assert!(query.matches("Breaking.Bad.S02E03-E07.VOSTFR"));
+        assert!(query.matches("Breaking Bad 2x05 VF"));
+        assert!(query.matches("Breaking Bad Saison 2 Episode 5 FRENCH"));
+        assert!(query.matches("Breaking.Bad.S02.MULTi.1080p.BluRay"));
+        assert!(query.matches("Breaking Bad - Saison 2 [Complète] FRENCH"));
+        assert!(query.matches("Breaking Bad S01-S05 Intégrale MULTi"));
+        assert!(query.matches("Breaking Bad - L'intégrale FRENCH"));
+
+        assert!(!query.matches("Breaking.Bad.S02E06.MULTi.1080p"));
+        assert!(!query.matches("Breaking.Bad.S03E05.MULTi.1080p"));
+        assert!(!query.matches("Breaking.Bad.S03.MULTi.1080p"));
+        assert!(!query.matches("Breaking Bad Saisons 3 à 5 Intégrale"));
+        assert!(!query.matches("Breaking Bad 2008 MULTi"));
+    }
+
+    #[test]
+    fn test_season_matches() {
+        let query = EpisodeQuery::new(Some(2), None).unwrap();
+        assert!(query.matches("Breaking.Bad.S02.MULTi.1080p.BluRay"));
+        assert!(query.matches("Breaking.Bad.S02E06.MULTi.1080p"));
+        assert!(query.matches("Breaking Bad Saison 02 FRENCH"));
+        assert!(!query.matches("Breaking.Bad.S01E06.MULTi.1080p"));
+    }
+}
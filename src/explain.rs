@@ -0,0 +1,145 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Diagnostics of a search, returned with `explain=true`: how TMDB/IMDB/TVDB ids were
+/// resolved, which YGG queries ran and how their results were picked.
+#[derive(Debug, Default, Serialize)]
+pub struct Explain {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub database: Option<DatabaseLookup>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub record: Option<TmdbRecord>,
+    pub queries: Vec<String>,
+    pub searches: Vec<QueryReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outcome: Option<String>,
+    pub session_renewed: bool,
+    pub elapsed_ms: u64,
+}
+
+/// Lookup of the searched id on TMDB.
+#[derive(Debug, Serialize)]
+pub struct DatabaseLookup {
+    pub source: &'static str,
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub elapsed_ms: u64,
+}
+
+/// TMDB record the titles were built from.
+#[derive(Debug, Serialize)]
+pub struct TmdbRecord {
+    pub media_type: &'static str,
+    pub tmdb_id: u64,
+    pub year: u32,
+    pub title: String,
+    pub original_title: String,
+}
+
+/// A YGG search of the request. `count` is the number of results left once the
+/// filters are applied, and `decision` tells what was done with them.
+#[derive(Debug, Serialize)]
+pub struct QueryReport {
+    pub query: String,
+    pub category: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decision: Option<&'static str>,
+    pub elapsed_ms: u64,
+}
+
+impl QueryReport {
+    pub fn new(
+        query: &str,
+        category: Option<usize>,
+        count: Result<usize, String>,
+        elapsed: Duration,
+    ) -> Self {
+        let (count, error) = match count {
+            Ok(count) => (Some(count), None),
+            Err(e) => (None, Some(e)),
+        };
+        QueryReport {
+            query: query.to_string(),
+            category,
+            count,
+            error,
+            decision: None,
+            elapsed_ms: elapsed.as_millis() as u64,
+        }
+    }
+}
+
+tokio::task_local! {
+    static EXPLAIN_SCOPE: Mutex<Explain>;
+}
+
+/// Runs a request, collecting its diagnostics when `enabled`.
+pub async fn with_explain_scope<F: Future>(enabled: bool, fut: F) -> (F::Output, Option<Explain>) {
+    if !enabled {
+        return (fut.await, None);
+    }
+    let start = Instant::now();
+    EXPLAIN_SCOPE
+        .scope(Mutex::new(Explain::default()), async {
+            let output = fut.await;
+            let explain = EXPLAIN_SCOPE.with(|scope| {
+                let mut explain = std::mem::take(&mut *scope.lock().unwrap());
+                explain.elapsed_ms = start.elapsed().as_millis() as u64;
+                explain
+            });
+            (output, Some(explain))
+        })
+        .await
+}
+
+/// Updates the diagnostics of the current request. Does nothing outside of an
+/// explain scope.
+pub fn record(update: impl FnOnce(&mut Explain)) {
+    let _ = EXPLAIN_SCOPE.try_with(|scope| {
+        if let Ok(mut explain) = scope.lock() {
+            update(&mut explain);
+        }
+    });
+}
+
+/// Awaits a future and measures how long it took.
+pub async fn timed<F: Future>(fut: F) -> (F::Output, Duration) {
+    let start = Instant::now();
+    let output = fut.await;
+    (output, start.elapsed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_explain_scope() {
+        let ((), explain) = with_explain_scope(true, async {
+            record(|explain| explain.queries.push("Moana 2016".to_string()));
+            let (count, elapsed) = timed(async { Ok(3) }).await;
+            record(|explain| {
+                explain
+                    .searches
+                    .push(QueryReport::new("Moana 2016", Some(2183), count, elapsed))
+            });
+        })
+        .await;
+        let explain = explain.unwrap();
+        assert_eq!(explain.queries, vec!["Moana 2016"]);
+        assert_eq!(explain.searches[0].count, Some(3));
+        assert!(explain.searches[0].error.is_none());
+
+        let ((), explain) = with_explain_scope(false, async {
+            record(|explain| explain.queries.push("ignored".to_string()));
+        })
+        .await;
+        assert!(explain.is_none());
+    }
+}
@@ -0,0 +1,255 @@
+use crate::parser::Torrent;
+use crate::release::RELEASE_FILTERS;
+use crate::rest::search::non_empty;
+use qstring::QString;
+use regex::{Regex, RegexBuilder};
+
+/// Post-filters applied to search results before they are returned.
+#[derive(Debug, Clone, Default)]
+pub struct ResultFilters {
+    pub min_seeders: Option<usize>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    /// Oldest accepted upload, as a unix timestamp (from `max_age` or `since`)
+    pub since: Option<usize>,
+    pub include: Option<Regex>,
+    pub exclude: Option<Regex>,
+    /// Release attributes (`resolution=1080p,2160p`...), any listed value matches
+    pub release: Vec<(&'static str, Vec<String>)>,
+}
+
+impl ResultFilters {
+    pub fn from_query(qs: &QString) -> Result<Self, String> {
+        let now = chrono::Utc::now().timestamp() as usize;
+
+        // Cardigann definitions send every input, empty ones included
+        let max_age = non_empty(qs.get("max_age"))
+            .map(|s| parse_duration(&s).ok_or(format!("Invalid max_age: {}", s)))
+            .transpose()?
+            .map(|age| now.saturating_sub(age));
+        let since = non_empty(qs.get("since"))
+            .map(|s| parse_date(&s).ok_or(format!("Invalid since: {}", s)))
+            .transpose()?;
+
+        Ok(ResultFilters {
+            min_seeders: non_empty(qs.get("min_seeders"))
+                .map(|s| s.parse().map_err(|_| format!("Invalid min_seeders: {}", s)))
+                .transpose()?,
+            min_size: parse_size_param(qs, "min_size")?,
+            max_size: parse_size_param(qs, "max_size")?,
+            since: max_age.max(since),
+            include: parse_regex_param(qs, "include")?,
+            exclude: parse_regex_param(qs, "exclude")?,
+            release: RELEASE_FILTERS
+                .iter()
+                .filter_map(|field| {
+                    let values: Vec<String> = qs
+                        .get(field)?
+                        .split(',')
+                        .map(|v| v.trim().to_lowercase())
+                        .filter(|v| !v.is_empty())
+                        .collect();
+                    Some((*field, values)).filter(|(_, values)| !values.is_empty())
+                })
+                .collect(),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min_seeders.is_none()
+            && self.min_size.is_none()
+            && self.max_size.is_none()
+            && self.since.is_none()
+            && self.include.is_none()
+            && self.exclude.is_none()
+            && self.release.is_empty()
+    }
+
+    pub fn matches(&self, torrent: &Torrent) -> bool {
+        self.min_seeders.is_none_or(|min| torrent.seed >= min)
+            && self.min_size.is_none_or(|min| torrent.size >= min)
+            && self.max_size.is_none_or(|max| torrent.size <= max)
+            && self.since.is_none_or(|since| torrent.age_stamp >= since)
+            && self
+                .include
+                .as_ref()
+                .is_none_or(|re| re.is_match(&torrent.name))
+            && self
+                .exclude
+                .as_ref()
+                .is_none_or(|re| !re.is_match(&torrent.name))
+            && self
+                .release
+                .iter()
+                .all(|(field, values)| release_matches(torrent, field, values))
+    }
+
+    pub fn apply(&self, torrents: Vec<Torrent>) -> Vec<Torrent> {
+        if self.is_empty() {
+            return torrents;
+        }
+        torrents.into_iter().filter(|t| self.matches(t)).collect()
+    }
+}
+
+fn release_matches(torrent: &Torrent, field: &str, values: &[String]) -> bool {
+    let actual = torrent.release.field_values(field);
+    // hdr=true / hdr=false only check for the presence of a HDR flag
+    match (field, values) {
+        ("hdr", [value]) if value == "true" => !actual.is_empty(),
+        ("hdr", [value]) if value == "false" => actual.is_empty(),
+        _ => actual
+            .iter()
+            .any(|a| values.iter().any(|v| a.to_lowercase() == *v)),
+    }
+}
+
+fn parse_size_param(qs: &QString, name: &str) -> Result<Option<u64>, String> {
+    non_empty(qs.get(name))
+        .map(|s| parse_size(&s).ok_or(format!("Invalid {}: {}", name, s)))
+        .transpose()
+}
+
+fn parse_regex_param(qs: &QString, name: &str) -> Result<Option<Regex>, String> {
+    qs.get(name)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            RegexBuilder::new(s)
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| format!("Invalid {} regex: {}", name, e))
+        })
+        .transpose()
+}
+
+/// Parses a size in bytes, or with a unit: `700MB`, `1.5G`, `4 Go`, `2GiB`...
+/// Units are powers of 1024, like the sizes displayed by YGG.
+fn parse_size(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.' && c != ',')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.replace(',', ".").parse().ok()?;
+
+    let exponent = match unit.trim().to_lowercase().as_str() {
+        "" | "b" | "o" => 0,
+        "k" | "kb" | "kib" | "ko" => 1,
+        "m" | "mb" | "mib" | "mo" => 2,
+        "g" | "gb" | "gib" | "go" => 3,
+        "t" | "tb" | "tib" | "to" => 4,
+        _ => return None,
+    };
+    Some((number * 1024f64.powi(exponent)).round() as u64)
+}
+
+/// Parses a duration in seconds, or with a unit: `30m`, `12h`, `7d`, `2w`.
+fn parse_duration(value: &str) -> Option<usize> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: usize = number.parse().ok()?;
+
+    let multiplier = match unit.trim() {
+        "" | "s" => 1,
+        "m" | "min" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        "w" => 604800,
+        _ => return None,
+    };
+    number.checked_mul(multiplier)
+}
+
+/// Parses a unix timestamp, a `YYYY-MM-DD` date or a RFC 3339 date.
+fn parse_date(value: &str) -> Option<usize> {
+    let value = value.trim();
+    if let Ok(timestamp) = value.parse::<usize>() {
+        return Some(timestamp);
+    }
+    if let Ok(date) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Some(date.timestamp().max(0) as usize);
+    }
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|date| date.and_utc().timestamp().max(0) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+    use crate::release::ReleaseInfo;
+
+    fn torrent(name: &str, seed: usize, size: u64, age_stamp: usize) -> Torrent {
+        Torrent {
+            name: name.to_string(),
+            age_stamp,
+            size,
+            seed,
+            release: ReleaseInfo::parse(name),
+            ..fixtures::torrent(1)
+        }
+    }
+
+    #[test]
+    fn test_parse_units() {
+        assert_eq!(parse_size("1024"), Some(1024));
+        assert_eq!(parse_size("700MB"), Some(700 * 1024 * 1024));
+        assert_eq!(parse_size("1.5 Go"), Some(1610612736));
+        assert_eq!(parse_size("2GiB"), Some(2147483648));
+        assert_eq!(parse_size("2 parsecs"), None);
+
+        assert_eq!(parse_duration("3600"), Some(3600));
+        assert_eq!(parse_duration("12h"), Some(43200));
+        assert_eq!(parse_duration("7d"), Some(604800));
+        assert_eq!(parse_duration("7y"), None);
+        assert_eq!(parse_duration("9999999999999999999w"), None);
+
+        assert_eq!(parse_date("1738044926"), Some(1738044926));
+        assert_eq!(parse_date("2025-01-28"), Some(1738022400));
+        assert_eq!(parse_date("2025-01-28T06:15:26Z"), Some(1738044926));
+        assert_eq!(parse_date("yesterday"), None);
+    }
+
+    #[test]
+    fn test_filters() {
+        let qs = QString::from(
+            "min_seeders=5&min_size=1GB&max_size=10GB&since=2025-01-01&include=1080p&exclude=vfq|cam",
+        );
+        let filters = ResultFilters::from_query(&qs).unwrap();
+
+        let gb = 1024 * 1024 * 1024;
+        let recent = 1738044926;
+        assert!(filters.matches(&torrent("Movie.2024.1080p.WEB", 10, 4 * gb, recent)));
+        assert!(filters.matches(&torrent("Movie.2024.1080P.WEB", 5, gb, recent)));
+        assert!(!filters.matches(&torrent("Movie.2024.1080p.WEB", 4, 4 * gb, recent)));
+        assert!(!filters.matches(&torrent("Movie.2024.1080p.WEB", 10, 11 * gb, recent)));
+        assert!(!filters.matches(&torrent("Movie.2024.1080p.WEB", 10, gb / 2, recent)));
+        assert!(!filters.matches(&torrent("Movie.2024.1080p.WEB", 10, 4 * gb, 1700000000)));
+        assert!(!filters.matches(&torrent("Movie.2024.720p.WEB", 10, 4 * gb, recent)));
+        assert!(!filters.matches(&torrent("Movie.2024.1080p.VFQ", 10, 4 * gb, recent)));
+
+        let qs = QString::from("resolution=2160p,1080p&language=vff&hdr=true");
+        let filters = ResultFilters::from_query(&qs).unwrap();
+        let torrent = |name| torrent(name, 10, gb, recent);
+        assert!(filters.matches(&torrent("Movie.2024.VFF.2160p.HDR.x265")));
+        assert!(filters.matches(&torrent("Movie.2024.MULTi.VFF.1080p.DV.x265")));
+        assert!(!filters.matches(&torrent("Movie.2024.VFF.720p.HDR.x265")));
+        assert!(!filters.matches(&torrent("Movie.2024.VFQ.2160p.HDR.x265")));
+        assert!(!filters.matches(&torrent("Movie.2024.VFF.2160p.x265")));
+
+        assert!(
+            ResultFilters::from_query(&QString::from(""))
+                .unwrap()
+                .is_empty()
+        );
+        let qs = QString::from("min_seeders=&min_size=&max_size=&max_age=&since=&include=");
+        assert!(ResultFilters::from_query(&qs).unwrap().is_empty());
+        assert!(ResultFilters::from_query(&QString::from("include=(")).is_err());
+        assert!(ResultFilters::from_query(&QString::from("max_age=soon")).is_err());
+    }
+}
@@ -0,0 +1,60 @@
+//! Fixtures shared by the unit tests.
+
+use crate::categories::Category;
+use crate::parser::Torrent;
+
+/// Film/Vidéo and Audio, with a few of their sub-categories.
+pub fn categories() -> Vec<Category> {
+    let sub = |id, name: &str| Category {
+        id,
+        name: name.to_string(),
+        ..Default::default()
+    };
+    vec![
+        Category {
+            id: 2145,
+            name: "Film/Vidéo".to_string(),
+            sub_categories: vec![
+                sub(2178, "Animation"),
+                sub(2183, "Film"),
+                sub(2184, "Série TV"),
+            ],
+            ..Default::default()
+        },
+        Category {
+            id: 2139,
+            name: "Audio".to_string(),
+            sub_categories: vec![sub(2148, "Musique")],
+            ..Default::default()
+        },
+    ]
+}
+
+/// Minimal search result in the Film sub-category.
+pub fn torrent(id: usize) -> Torrent {
+    Torrent {
+        category_id: 2183,
+        name: format!("Torrent {}", id),
+        id,
+        ..Default::default()
+    }
+}
+
+/// Search result with every field filled, as rendered in the feeds.
+pub fn feed_torrent() -> Torrent {
+    Torrent {
+        category_id: 2183,
+        name: "Vaiana & co <2016>".to_string(),
+        id: 1234,
+        comments_count: 2,
+        age_stamp: 1738044926,
+        size: 3189013217,
+        completed: 15,
+        seed: 10,
+        leech: 3,
+        info_url: "/torrent/info/film/1234-vaiana".to_string(),
+        link: "https://ygg.example/torrent/film/1234-vaiana".to_string(),
+        infohash: Some("0123456789abcdef0123456789abcdef01234567".to_string()),
+        ..Default::default()
+    }
+}
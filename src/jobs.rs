@@ -0,0 +1,312 @@
+use crate::torrent::download_torrent_file;
+use crate::ygg_client::YggClient;
+use actix_web::web::Bytes;
+use serde_json::{Value, json};
+use sha1::{Digest, Sha1};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::Semaphore;
+
+/// Wait imposed by YGG between the download token and the file, in seconds.
+pub const DOWNLOAD_WAIT: i64 = 30;
+
+/// How long finished jobs are kept, in seconds.
+const JOB_TTL: i64 = 3600;
+
+/// Jobs downloading at the same time, the others wait for a free slot.
+const MAX_RUNNING_JOBS: usize = 4;
+
+/// Jobs queued or downloading at the same time, new ones are refused past it.
+const MAX_QUEUED_JOBS: usize = 32;
+
+/// Finished jobs kept, the oldest ones go first past it.
+const MAX_FINISHED_JOBS: usize = 64;
+
+static JOBS: Mutex<BTreeMap<String, Job>> = Mutex::new(BTreeMap::new());
+static JOB_COUNTER: AtomicU64 = AtomicU64::new(0);
+static JOB_SLOTS: Semaphore = Semaphore::const_new(MAX_RUNNING_JOBS);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobStatus {
+    /// Waiting for a free download slot
+    Queued,
+    Pending,
+    Ready,
+    Failed,
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Pending => "pending",
+            JobStatus::Ready => "ready",
+            JobStatus::Failed => "failed",
+        }
+    }
+}
+
+/// Background download of a .torrent file. Times are unix timestamps.
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub id: String,
+    pub torrent_id: usize,
+    pub status: JobStatus,
+    pub created_at: i64,
+    /// When the file should be ready, set once the job gets a download slot.
+    pub eta: i64,
+    /// YGG wait of the download, in seconds.
+    wait: i64,
+    pub finished_at: Option<i64>,
+    pub error: Option<String>,
+    pub file: Option<Bytes>,
+    /// Session the job was made with, None for the shared account. Jobs of a
+    /// custom session are only visible to that session.
+    owner: Option<String>,
+}
+
+impl Job {
+    /// Made with the shared account, so it can be reused by other requests.
+    fn is_shared(&self) -> bool {
+        self.owner.is_none()
+    }
+
+    /// Seconds left until the file should be ready, the whole wait while queued.
+    pub fn remaining(&self) -> i64 {
+        match self.status {
+            JobStatus::Queued => self.wait,
+            JobStatus::Pending => (self.eta - now()).max(0),
+            _ => 0,
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut json = json!({
+            "id": self.id,
+            "torrent_id": self.torrent_id,
+            "status": self.status.as_str(),
+            "created_at": self.created_at,
+            "eta": self.eta,
+            "remaining_seconds": self.remaining(),
+        });
+        if let Some(error) = &self.error {
+            json["error"] = json!(error);
+        }
+        json
+    }
+}
+
+/// Identifies the custom session of a request by a hash of its cookies, so they
+/// aren't kept in memory.
+pub fn session_owner(cookies: &str) -> String {
+    Sha1::digest(cookies.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+fn job_id(torrent_id: usize) -> String {
+    let seed = format!(
+        "{}-{}-{}",
+        JOB_COUNTER.fetch_add(1, Ordering::Relaxed),
+        chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default(),
+        torrent_id
+    );
+    Sha1::digest(seed.as_bytes())
+        .iter()
+        .take(8)
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Drops the jobs finished for more than `JOB_TTL`, then the oldest finished
+/// ones past `MAX_FINISHED_JOBS`.
+fn purge(jobs: &mut BTreeMap<String, Job>, now: i64) {
+    jobs.retain(|_, job| {
+        job.finished_at
+            .is_none_or(|finished| now - finished < JOB_TTL)
+    });
+
+    let mut finished: Vec<(i64, String)> = jobs
+        .values()
+        .filter_map(|job| job.finished_at.map(|finished| (finished, job.id.clone())))
+        .collect();
+    finished.sort();
+    let excess = finished.len().saturating_sub(MAX_FINISHED_JOBS);
+    for (_, id) in finished.into_iter().take(excess) {
+        jobs.remove(&id);
+    }
+}
+
+/// Starts downloading a torrent in the background, the token request and the YGG
+/// wait included. A job of the shared account still pending or ready for the same
+/// torrent is returned instead of starting a new one. `owner` is the custom
+/// session of the request, if any (see `session_owner`). Returns None when
+/// `MAX_QUEUED_JOBS` jobs are already queued or downloading.
+pub fn prepare(
+    client: YggClient,
+    torrent_id: usize,
+    turbo_enabled: bool,
+    owner: Option<String>,
+) -> Result<Option<Job>, Box<dyn std::error::Error>> {
+    let shared = owner.is_none();
+    let now = now();
+    let mut jobs = JOBS.lock()?;
+    purge(&mut jobs, now);
+
+    let running = jobs.values().find(|job| {
+        job.is_shared() && job.torrent_id == torrent_id && job.status != JobStatus::Failed
+    });
+    if let Some(job) = running.filter(|_| shared) {
+        debug!("Reusing job {} for torrent {}", job.id, torrent_id);
+        return Ok(Some(job.clone()));
+    }
+
+    let unfinished = jobs.values().filter(|job| job.finished_at.is_none());
+    if unfinished.count() >= MAX_QUEUED_JOBS {
+        warn!("Too many download jobs, refusing torrent {}", torrent_id);
+        return Ok(None);
+    }
+
+    let wait = match turbo_enabled {
+        true => 0,
+        false => DOWNLOAD_WAIT,
+    };
+    let job = Job {
+        id: job_id(torrent_id),
+        torrent_id,
+        status: JobStatus::Queued,
+        created_at: now,
+        eta: now + wait,
+        wait,
+        finished_at: None,
+        error: None,
+        file: None,
+        owner,
+    };
+    jobs.insert(job.id.clone(), job.clone());
+    drop(jobs);
+
+    info!("Starting job {} for torrent {}", job.id, torrent_id);
+    tokio::spawn(run(
+        client,
+        job.id.clone(),
+        torrent_id,
+        turbo_enabled,
+        shared,
+    ));
+    Ok(Some(job))
+}
+
+async fn run(client: YggClient, id: String, torrent_id: usize, turbo_enabled: bool, shared: bool) {
+    let Ok(_permit) = JOB_SLOTS.acquire().await else {
+        return;
+    };
+    if let Some(job) = JOBS.lock().ok().as_mut().and_then(|jobs| jobs.get_mut(&id)) {
+        job.status = JobStatus::Pending;
+        job.eta = now() + job.wait;
+    }
+    let result = download_torrent_file(&client, torrent_id, turbo_enabled, shared)
+        .await
+        .map_err(|e| e.to_string());
+
+    let Ok(mut jobs) = JOBS.lock() else {
+        return;
+    };
+    let Some(job) = jobs.get_mut(&id) else {
+        return;
+    };
+    job.finished_at = Some(now());
+    match result {
+        Ok(bytes) => {
+            debug!("Job {} is ready ({} bytes)", id, bytes.len());
+            job.status = JobStatus::Ready;
+            job.file = Some(Bytes::from(bytes));
+        }
+        Err(e) => {
+            warn!("Job {} failed: {}", id, e);
+            job.status = JobStatus::Failed;
+            job.error = Some(e);
+        }
+    }
+}
+
+/// Returns a job by id, if visible to the session `owner`. The file is shared
+/// with the stored job, not copied.
+pub fn get(id: &str, owner: Option<&str>) -> Option<Job> {
+    JOBS.lock()
+        .ok()?
+        .get(id)
+        .filter(|job| job.is_shared() || job.owner.as_deref() == owner)
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(id: &str, status: JobStatus, finished_at: Option<i64>) -> Job {
+        Job {
+            id: id.to_string(),
+            torrent_id: 1234,
+            status,
+            created_at: 0,
+            eta: 30,
+            wait: 30,
+            finished_at,
+            error: None,
+            file: None,
+            owner: None,
+        }
+    }
+
+    #[test]
+    fn test_purge() {
+        let mut jobs = BTreeMap::new();
+        jobs.insert("a".to_string(), job("a", JobStatus::Pending, None));
+        jobs.insert("b".to_string(), job("b", JobStatus::Ready, Some(100)));
+        jobs.insert("c".to_string(), job("c", JobStatus::Failed, Some(4000)));
+        purge(&mut jobs, 4000);
+        assert_eq!(jobs.keys().collect::<Vec<_>>(), vec!["a", "c"]);
+
+        for i in 0..MAX_FINISHED_JOBS + 2 {
+            let id = format!("f{:03}", i);
+            jobs.insert(id.clone(), job(&id, JobStatus::Ready, Some(i as i64)));
+        }
+        purge(&mut jobs, 100);
+        assert_eq!(jobs.len(), MAX_FINISHED_JOBS + 1);
+        assert!(jobs.contains_key("a"));
+        assert!(jobs.contains_key("c") && jobs.contains_key("f003"));
+        assert!(!jobs.contains_key("f002"));
+    }
+
+    #[test]
+    fn test_job_json() {
+        let ready = job("a", JobStatus::Ready, Some(30));
+        let json = ready.to_json();
+        assert_eq!(json["status"], "ready");
+        assert_eq!(json["remaining_seconds"], 0);
+        assert!(json.get("error").is_none());
+        assert_eq!(job("b", JobStatus::Queued, None).remaining(), 30);
+
+        assert_ne!(job_id(1234), job_id(1234));
+        assert_eq!(job_id(1234).len(), 16);
+    }
+
+    #[test]
+    fn test_job_owner() {
+        let owner = session_owner("ygg_=abc");
+        let mut owned = job("owned", JobStatus::Pending, None);
+        owned.owner = Some(owner.clone());
+        JOBS.lock().unwrap().insert(owned.id.clone(), owned);
+
+        assert!(get("owned", Some(&owner)).is_some());
+        assert!(get("owned", None).is_none());
+        assert!(get("owned", Some(&session_owner("ygg_=def"))).is_none());
+    }
+}
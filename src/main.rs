@@ -1,14 +1,31 @@
 mod auth;
+mod bencode;
+mod cache;
 mod categories;
 mod config;
 mod dbs;
 mod domain;
+mod download_clients;
+mod episode;
+mod explain;
+mod filters;
+#[cfg(test)]
+mod fixtures;
 mod flaresolverr;
+mod jobs;
+mod nfo;
 mod parser;
 mod rate_limiter;
+mod release;
+mod relevance;
 pub mod resolver;
 mod rest;
+mod rss;
 mod search;
+mod top;
+mod torrent;
+mod torrent_cache;
+mod torznab;
 mod user;
 mod utils;
 mod ygg_client;
@@ -152,6 +169,39 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         );
     }
 
+    cache::init_search_cache(
+        config.search_cache_ttl.unwrap_or(cache::DEFAULT_TTL),
+        config
+            .search_cache_size
+            .unwrap_or(cache::DEFAULT_MAX_ENTRIES),
+    );
+    match cache::search_cache().is_enabled() {
+        true => info!(
+            "Search cache enabled (ttl: {}s, max entries: {})",
+            config.search_cache_ttl.unwrap_or(cache::DEFAULT_TTL),
+            config
+                .search_cache_size
+                .unwrap_or(cache::DEFAULT_MAX_ENTRIES)
+        ),
+        false => info!("Search cache disabled"),
+    }
+
+    if let Some(dir) = &config.torrent_cache_dir {
+        let max_size = config
+            .torrent_cache_size
+            .unwrap_or(torrent_cache::DEFAULT_MAX_SIZE);
+        let max_age = config
+            .torrent_cache_max_age
+            .unwrap_or(torrent_cache::DEFAULT_MAX_AGE);
+        match torrent_cache::init_torrent_cache(dir, max_size, max_age) {
+            Ok(()) => info!(
+                "Torrent cache enabled in {} (max size: {}MB, max age: {}s)",
+                dir, max_size, max_age
+            ),
+            Err(e) => warn!("Failed to initialize torrent cache in {}: {}", dir, e),
+        }
+    }
+
     let config_clone = config.clone();
     HttpServer::new(move || {
         App::new()
@@ -0,0 +1,99 @@
+use crate::DOMAIN;
+use crate::utils::check_session_expired;
+use crate::ygg_client::YggClient;
+use scraper::{Html, Selector};
+use std::sync::OnceLock;
+
+/// Upper half (0x80-0xFF) of code page 437, the charset of most NFO files.
+const CP437_HIGH: &str = "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒáíóúñÑªº¿⌐¬½¼¡«»\
+░▒▓│┤╡╢╖╕╣║╗╝╜╛┐└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀\
+αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}";
+
+fn cp437_high() -> &'static [char] {
+    static CHARS: OnceLock<Vec<char>> = OnceLock::new();
+    CHARS.get_or_init(|| CP437_HIGH.chars().collect())
+}
+
+/// Decodes CP437 bytes to UTF-8. Valid UTF-8 input (NFO already converted by the
+/// uploader or by YGG) is returned as is.
+pub fn decode_cp437(bytes: &[u8]) -> String {
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return text.to_string();
+    }
+    bytes
+        .iter()
+        .map(|&b| match b {
+            0x00..=0x7F => b as char,
+            _ => cp437_high()[(b - 0x80) as usize],
+        })
+        .collect()
+}
+
+/// Fetches the NFO of a torrent through the `/engine/get_nfo` endpoint used by
+/// the NFO button of the search results.
+pub async fn get_nfo(client: &YggClient, id: usize) -> Result<String, Box<dyn std::error::Error>> {
+    let domain = {
+        let domain_lock = DOMAIN.lock()?;
+        domain_lock.clone()
+    };
+
+    let url = format!("https://{}/engine/get_nfo?torrent={}", domain, id);
+    let response = client.get_bytes(&url).await?;
+
+    if check_session_expired(response.status, &response.url) {
+        return Err("Session expired".into());
+    }
+    if response.status == 404 {
+        return Err("NFO not found".into());
+    }
+    if !(200..300).contains(&response.status) {
+        return Err(format!("Failed to get NFO: {}", response.status).into());
+    }
+
+    let nfo = parse_nfo(&decode_cp437(&response.bytes))?;
+    if nfo.trim().is_empty() {
+        return Err("NFO not found".into());
+    }
+    Ok(nfo)
+}
+
+/// Extracts the NFO text, which YGG may wrap in a `<pre>` block.
+pub fn parse_nfo(body: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let text = match body.contains("<pre") {
+        true => {
+            let document = Html::parse_fragment(body);
+            document
+                .select(&Selector::parse("pre")?)
+                .next()
+                .map(|pre| pre.text().collect::<String>())
+                .unwrap_or_default()
+        }
+        false => body.to_string(),
+    };
+    Ok(text.replace("\r\n", "\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_cp437() {
+        assert_eq!(cp437_high().len(), 128);
+        assert_eq!(decode_cp437(b"Video : x265\r\n"), "Video : x265\r\n");
+        assert_eq!(
+            decode_cp437(&[0xDB, 0xB2, 0xB1, 0xB0, b' ', 0x82]),
+            "█▓▒░ é"
+        );
+        assert_eq!(decode_cp437("déjà".as_bytes()), "déjà");
+    }
+
+    #[test]
+    fn test_parse_nfo() {
+        assert_eq!(
+            parse_nfo("<pre>General\r\nFormat : Matroska &amp; co</pre>").unwrap(),
+            "General\nFormat : Matroska & co"
+        );
+        assert_eq!(parse_nfo("General\r\n").unwrap(), "General\n");
+    }
+}
@@ -1,11 +1,13 @@
 use crate::DOMAIN;
+use crate::categories::newznab_categories;
+use crate::release::ReleaseInfo;
 use crate::search::{Order, Sort};
-use scraper::{Html, Selector};
+use scraper::{ElementRef, Html, Selector};
 use serde::Serialize;
 use serde_json::Value;
 use std::cmp::PartialEq;
 
-#[derive(Debug, Serialize, Clone, Eq, Hash, PartialEq)]
+#[derive(Debug, Default, Serialize, Clone, Eq, Hash, PartialEq)]
 pub struct Torrent {
     pub category_id: usize,
     pub name: String,
@@ -18,6 +20,13 @@ pub struct Torrent {
     pub leech: usize,
     pub info_url: String,
     pub link: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub infohash: Option<String>,
+    #[serde(flatten)]
+    pub release: ReleaseInfo,
+    /// Match score against the searched titles, set with `Sort::Relevance`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relevance: Option<i32>,
 }
 
 impl PartialEq for Order {
@@ -45,10 +54,22 @@ impl Torrent {
         Ok(format!("/torrent/{}", self.id))
     }
 
-    pub fn to_json(&self) -> Value {
+    pub fn get_magnet(&self) -> Option<String> {
+        self.infohash
+            .as_ref()
+            .and_then(|infohash| crate::torrent::magnet_uri(infohash, &self.name))
+    }
+
+    /// JSON of a search result. The magnet link announces with the passkey of the
+    /// shared account, so `with_magnet` is false for requests with custom cookies.
+    pub fn to_json(&self, with_magnet: bool) -> Value {
         let mut value = serde_json::to_value(self).unwrap();
         value["url"] = Value::String(self.get_url().unwrap());
         value["download"] = Value::String(self.get_download_url().unwrap());
+        value["newznab_categories"] = serde_json::json!(newznab_categories(self.category_id));
+        if let Some(magnet) = self.get_magnet().filter(|_| with_magnet) {
+            value["magnet"] = Value::String(magnet);
+        }
         value
     }
 
@@ -99,6 +120,13 @@ impl Torrent {
                     torrents.sort_by(|a, b| b.leech.cmp(&a.leech));
                 }
             }
+            Sort::Relevance => {
+                if order == Order::Ascending {
+                    torrents.sort_by_key(|t| (t.relevance, t.seed));
+                } else {
+                    torrents.sort_by_key(|t| std::cmp::Reverse((t.relevance, t.seed)));
+                }
+            }
         }
     }
 }
@@ -109,7 +137,6 @@ pub fn extract_torrents(body: &str) -> Result<Vec<Torrent>, Box<dyn std::error::
         return Ok(Vec::new());
     }
 
-    let mut torrents = Vec::new();
     let doc = Html::parse_document(body);
 
     let table_selector = Selector::parse("#\\#torrents div.table-responsive > table > tbody")?;
@@ -122,6 +149,36 @@ pub fn extract_torrents(body: &str) -> Result<Vec<Torrent>, Box<dyn std::error::
         table.select(&Selector::parse("tr")?).count()
     );
 
+    let torrents = parse_rows(table)?;
+
+    debug!("Parsed {} torrents", torrents.len());
+
+    Ok(torrents)
+}
+
+/// Extracts the torrents of every result table of a page, such as the top pages
+/// which have one table per category.
+pub fn extract_all_torrents(body: &str) -> Result<Vec<Torrent>, Box<dyn std::error::Error>> {
+    let doc = Html::parse_document(body);
+    let table_selector = Selector::parse("div.table-responsive > table > tbody")?;
+
+    let mut torrents: Vec<Torrent> = Vec::new();
+    for table in doc.select(&table_selector) {
+        for torrent in parse_rows(table)? {
+            if !torrents.iter().any(|t| t.id == torrent.id) {
+                torrents.push(torrent);
+            }
+        }
+    }
+
+    debug!("Parsed {} torrents", torrents.len());
+
+    Ok(torrents)
+}
+
+fn parse_rows(table: ElementRef) -> Result<Vec<Torrent>, Box<dyn std::error::Error>> {
+    let mut torrents = Vec::new();
+
     for row in table.select(&Selector::parse("tr")?) {
         let columns: Vec<_> = row.select(&Selector::parse("td")?).collect();
         if columns.len() < 9 {
@@ -222,6 +279,10 @@ pub fn extract_torrents(body: &str) -> Result<Vec<Torrent>, Box<dyn std::error::
             }
         };
 
+        let infohash = crate::torrent::known_infohash(id).map(|(infohash, _)| infohash);
+
+        let release = ReleaseInfo::parse(&name);
+
         torrents.push(Torrent {
             category_id,
             name,
@@ -234,17 +295,18 @@ pub fn extract_torrents(body: &str) -> Result<Vec<Torrent>, Box<dyn std::error::
             leech,
             info_url,
             link,
+            infohash,
+            release,
+            relevance: None,
         });
     }
 
-    debug!("Parsed {} torrents", torrents.len());
-
     Ok(torrents)
 }
 
 const SIZES: [&str; 5] = ["o", "ko", "Mo", "Go", "To"];
 
-fn human_readable_size_to_bytes(size: &str) -> Result<u64, Box<dyn std::error::Error>> {
+pub(crate) fn human_readable_size_to_bytes(size: &str) -> Result<u64, Box<dyn std::error::Error>> {
     let size = size.trim();
     let mut split_index = 0;
     let mut chars = size.chars();
@@ -0,0 +1,281 @@
+use crate::episode::season_episode;
+use regex::Regex;
+use serde::Serialize;
+use std::sync::OnceLock;
+use utoipa::ToSchema;
+
+/// Attributes parsed from a release name, e.g. `Moana.2.2024.MULTi.1080p.WEB-DL.H265-GROUP`.
+#[derive(Debug, Clone, Default, Serialize, ToSchema, PartialEq, Eq, Hash)]
+pub struct ReleaseInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub video_codec: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio_codec: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub hdr: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub languages: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub season: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub episode: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<u32>,
+}
+
+type Rules = Vec<(Regex, &'static str)>;
+
+/// Compiles `(pattern, value)` pairs matched as whole tokens. A trailing `+`
+/// belongs to the token, so that `HDR10` does not match `HDR10+`.
+fn compile(rules: &[(&str, &'static str)]) -> Rules {
+    rules
+        .iter()
+        .map(|(pattern, value)| {
+            let pattern = format!(r"(?:^|[^a-z0-9])(?:{})(?:[^a-z0-9+]|$)", pattern);
+            (Regex::new(&pattern).unwrap(), *value)
+        })
+        .collect()
+}
+
+fn resolution_rules() -> &'static Rules {
+    static RULES: OnceLock<Rules> = OnceLock::new();
+    RULES.get_or_init(|| {
+        compile(&[
+            (r"2160[pi]|4k|uhd", "2160p"),
+            (r"1080[pi]", "1080p"),
+            (r"720p", "720p"),
+            (r"576[pi]", "576p"),
+            (r"480[pi]", "480p"),
+        ])
+    })
+}
+
+fn source_rules() -> &'static Rules {
+    static RULES: OnceLock<Rules> = OnceLock::new();
+    RULES.get_or_init(|| {
+        compile(&[
+            (r"remux|bdremux", "Remux"),
+            (r"web-?rip", "WEBRip"),
+            (r"web-?dl|web", "WEB-DL"),
+            (r"hdlight|mhd|4klight", "HDLight"),
+            (r"blu-?ray|bdrip|brrip|bd", "BluRay"),
+            (r"hdtv|tvrip", "HDTV"),
+            (r"dvdrip", "DVDRip"),
+            (r"dvd|dvd5|dvd9", "DVD"),
+            (r"cam|ts|telesync|hdcam", "CAM"),
+        ])
+    })
+}
+
+fn video_codec_rules() -> &'static Rules {
+    static RULES: OnceLock<Rules> = OnceLock::new();
+    RULES.get_or_init(|| {
+        compile(&[
+            (r"[xh] ?265|hevc", "H265"),
+            (r"[xh] ?264|avc", "H264"),
+            (r"av1", "AV1"),
+            (r"xvid|divx", "XviD"),
+        ])
+    })
+}
+
+fn audio_codec_rules() -> &'static Rules {
+    static RULES: OnceLock<Rules> = OnceLock::new();
+    RULES.get_or_init(|| {
+        compile(&[
+            (r"truehd", "TrueHD"),
+            (r"dts-?hd(?: ?ma)?|dts-?x", "DTS-HD"),
+            (r"dts", "DTS"),
+            (r"e-?ac-?3|ddp(?:5 1)?|dd\+(?:5 1)?", "EAC3"),
+            (r"ac-?3|dd(?:5 1)?", "AC3"),
+            (r"aac", "AAC"),
+            (r"flac", "FLAC"),
+            (r"opus", "OPUS"),
+            (r"mp3", "MP3"),
+        ])
+    })
+}
+
+fn hdr_rules() -> &'static Rules {
+    static RULES: OnceLock<Rules> = OnceLock::new();
+    RULES.get_or_init(|| {
+        compile(&[
+            (r"hdr10\+|hdr10plus", "HDR10+"),
+            (r"hdr10", "HDR10"),
+            (r"hdr", "HDR"),
+            (r"dv|dovi|dolby ?vision", "DV"),
+            (r"hlg", "HLG"),
+        ])
+    })
+}
+
+fn language_rules() -> &'static Rules {
+    static RULES: OnceLock<Rules> = OnceLock::new();
+    RULES.get_or_init(|| {
+        compile(&[
+            (r"multi", "MULTi"),
+            (r"truefrench", "TRUEFRENCH"),
+            (r"french", "FRENCH"),
+            (r"vff", "VFF"),
+            (r"vfq", "VFQ"),
+            (r"vfi", "VFI"),
+            (r"vf2", "VF2"),
+            (r"vostfr|subfrench", "VOSTFR"),
+            (r"english|eng", "ENGLISH"),
+        ])
+    })
+}
+
+fn year_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\b(19[0-9]{2}|20[0-9]{2})\b").unwrap())
+}
+
+fn group_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"-([A-Za-z0-9_]+)$").unwrap())
+}
+
+fn first_match(rules: &Rules, name: &str) -> Option<String> {
+    rules
+        .iter()
+        .find(|(re, _)| re.is_match(name))
+        .map(|(_, value)| value.to_string())
+}
+
+fn all_matches(rules: &Rules, name: &str) -> Vec<String> {
+    let mut values: Vec<String> = Vec::new();
+    for (re, value) in rules {
+        if re.is_match(name) && !values.iter().any(|v| v == value) {
+            values.push(value.to_string());
+        }
+    }
+    values
+}
+
+impl ReleaseInfo {
+    pub fn parse(release_name: &str) -> Self {
+        let name = release_name.to_lowercase().replace(['.', '_'], " ");
+
+        // The first token is part of the title, even when it looks like a year ("1917")
+        let year = year_regex()
+            .captures_iter(&name)
+            .filter(|caps| caps.get(0).is_some_and(|m| m.start() > 0))
+            .filter_map(|caps| caps[1].parse().ok())
+            .last();
+
+        let (season, episode) = season_episode(release_name);
+
+        ReleaseInfo {
+            resolution: first_match(resolution_rules(), &name),
+            source: first_match(source_rules(), &name),
+            video_codec: first_match(video_codec_rules(), &name),
+            audio_codec: first_match(audio_codec_rules(), &name),
+            hdr: all_matches(hdr_rules(), &name),
+            languages: all_matches(language_rules(), &name),
+            group: parse_group(release_name),
+            season,
+            episode,
+            year,
+        }
+    }
+
+    /// Values of a field usable as a `/search` filter (see [`RELEASE_FILTERS`]).
+    pub fn field_values(&self, field: &str) -> Vec<String> {
+        match field {
+            "resolution" => self.resolution.iter().cloned().collect(),
+            "source" => self.source.iter().cloned().collect(),
+            "video_codec" => self.video_codec.iter().cloned().collect(),
+            "audio_codec" => self.audio_codec.iter().cloned().collect(),
+            "hdr" => self.hdr.clone(),
+            "language" => self.languages.clone(),
+            "group" => self.group.iter().cloned().collect(),
+            "year" => self.year.iter().map(|y| y.to_string()).collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// `/search` parameters filtering on release attributes.
+pub const RELEASE_FILTERS: [&str; 8] = [
+    "resolution",
+    "source",
+    "video_codec",
+    "audio_codec",
+    "hdr",
+    "language",
+    "group",
+    "year",
+];
+
+fn parse_group(release_name: &str) -> Option<String> {
+    let name = release_name.trim();
+    let name = name
+        .strip_suffix(".mkv")
+        .or(name.strip_suffix(".mp4"))
+        .or(name.strip_suffix(".avi"))
+        .unwrap_or(name);
+    group_regex()
+        .captures(name)
+        .map(|caps| caps[1].to_string())
+        .filter(|group| {
+            !group.chars().all(|c| c.is_ascii_digit())
+                && !["dl", "rip", "hd", "ray"].contains(&group.to_lowercase().as_str())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_movie() {
+        let info = ReleaseInfo::parse(
+            "Moana.2.2024.MULTi.TRUEFRENCH.2160p.WEB-DL.DV.HDR10.H265.DDP5.1-GROUP",
+        );
+        assert_eq!(info.resolution.as_deref(), Some("2160p"));
+        assert_eq!(info.source.as_deref(), Some("WEB-DL"));
+        assert_eq!(info.video_codec.as_deref(), Some("H265"));
+        assert_eq!(info.audio_codec.as_deref(), Some("EAC3"));
+        assert_eq!(info.hdr, vec!["HDR10", "DV"]);
+        assert_eq!(info.languages, vec!["MULTi", "TRUEFRENCH"]);
+        assert_eq!(info.group.as_deref(), Some("GROUP"));
+        assert_eq!(info.year, Some(2024));
+        assert_eq!((info.season, info.episode), (None, None));
+
+        let info = ReleaseInfo::parse("1917 (2019) VFF 1080p BluRay x264 AC3");
+        assert_eq!(info.resolution.as_deref(), Some("1080p"));
+        assert_eq!(info.source.as_deref(), Some("BluRay"));
+        assert_eq!(info.video_codec.as_deref(), Some("H264"));
+        assert_eq!(info.audio_codec.as_deref(), Some("AC3"));
+        assert_eq!(info.languages, vec!["VFF"]);
+        assert_eq!(info.year, Some(2019));
+        assert_eq!(info.group, None);
+    }
+
+    #[test]
+    fn test_parse_series() {
+        let info = ReleaseInfo::parse("Breaking.Bad.S02E05.VOSTFR.720p.HDTV.x264-NoTag");
+        assert_eq!(info.resolution.as_deref(), Some("720p"));
+        assert_eq!(info.source.as_deref(), Some("HDTV"));
+        assert_eq!(info.languages, vec!["VOSTFR"]);
+        assert_eq!(info.group.as_deref(), Some("NoTag"));
+        assert_eq!((info.season, info.episode), (Some(2), Some(5)));
+
+        let info = ReleaseInfo::parse("Breaking Bad Saison 3 FRENCH 1080p WEB-DL");
+        assert_eq!((info.season, info.episode), (Some(3), None));
+        assert_eq!(info.group, None);
+        assert!(info.hdr.is_empty());
+
+        let info = ReleaseInfo::parse("Dune.Part.Two.2024.HDR10+.2160p.WEBRip.DD+5.1.x265");
+        assert_eq!(info.hdr, vec!["HDR10+"]);
+        assert_eq!(info.source.as_deref(), Some("WEBRip"));
+        assert_eq!(info.audio_codec.as_deref(), Some("EAC3"));
+    }
+}
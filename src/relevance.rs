@@ -0,0 +1,184 @@
+use crate::categories::slugify;
+use crate::parser::Torrent;
+use crate::release::ReleaseInfo;
+
+/// Tokens that end the title part of a release name.
+const TITLE_END_TOKENS: [&str; 32] = [
+    "multi",
+    "truefrench",
+    "french",
+    "vff",
+    "vfq",
+    "vfi",
+    "vf2",
+    "vostfr",
+    "subfrench",
+    "2160p",
+    "1080p",
+    "720p",
+    "480p",
+    "4k",
+    "uhd",
+    "web",
+    "webrip",
+    "bluray",
+    "bdrip",
+    "hdlight",
+    "remux",
+    "hdtv",
+    "dvdrip",
+    "x264",
+    "x265",
+    "h264",
+    "h265",
+    "hevc",
+    "integrale",
+    "saison",
+    "season",
+    "complete",
+];
+
+/// Words of bonus material, rarely what a title search is looking for.
+const PENALTY_WORDS: [&str; 9] = [
+    "making of",
+    "bonus",
+    "extras",
+    "featurette",
+    "behind the scenes",
+    "bande annonce",
+    "trailer",
+    "soundtrack",
+    "sample",
+];
+
+fn tokens(text: &str) -> Vec<String> {
+    slugify(text)
+        .split('-')
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_string())
+        .collect()
+}
+
+fn as_year(token: &str) -> Option<u32> {
+    token
+        .parse::<u32>()
+        .ok()
+        .filter(|year| (1900..2100).contains(year) && token.len() == 4)
+}
+
+fn is_episode_token(token: &str) -> bool {
+    let mut chars = token.chars();
+    chars.next() == Some('s')
+        && chars.next().is_some_and(|c| c.is_ascii_digit())
+        && token.chars().skip(1).all(|c| c.is_ascii_alphanumeric())
+}
+
+/// Title tokens of a release name: everything before the year, the episode or the
+/// first technical tag. The first token is always kept ("1917", "S.W.A.T.").
+fn release_title_tokens(name: &str) -> Vec<String> {
+    let all = tokens(name);
+    let end = all
+        .iter()
+        .enumerate()
+        .skip(1)
+        .find(|(_, token)| {
+            as_year(token).is_some()
+                || is_episode_token(token)
+                || TITLE_END_TOKENS.contains(&token.as_str())
+        })
+        .map(|(idx, _)| idx)
+        .unwrap_or(all.len());
+    all.into_iter().take(end).collect()
+}
+
+/// Scores how well a release name matches a searched title (`Moana 2016`): share
+/// of the title words found, minus the extra words of the release title, with a
+/// bonus when the year matches and penalties for bonus material.
+pub fn score(release_name: &str, title: &str) -> i32 {
+    let mut query = tokens(title);
+    let expected_year = match query.last().and_then(|token| as_year(token)) {
+        Some(year) if query.len() > 1 => {
+            query.pop();
+            Some(year)
+        }
+        _ => None,
+    };
+    if query.is_empty() {
+        return 0;
+    }
+
+    let release = release_title_tokens(release_name);
+    let matched = query.iter().filter(|token| release.contains(token)).count();
+    let extra = release
+        .iter()
+        .filter(|token| !query.contains(token))
+        .count();
+    let mut score = (matched * 100 / query.len()) as i32 - 10 * extra as i32;
+
+    if let (Some(expected), Some(year)) = (expected_year, ReleaseInfo::parse(release_name).year) {
+        score += match expected == year {
+            true => 20,
+            false => -30,
+        };
+    }
+
+    let words = format!(" {} ", tokens(release_name).join(" "));
+    for penalty in PENALTY_WORDS {
+        if words.contains(&format!(" {} ", penalty)) {
+            score -= 40;
+        }
+    }
+    score
+}
+
+/// Sets the relevance of each torrent: its best score against the searched titles.
+pub fn score_torrents(torrents: &mut [Torrent], titles: &[String]) {
+    for torrent in torrents.iter_mut() {
+        torrent.relevance = titles.iter().map(|title| score(&torrent.name, title)).max();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_release_title_tokens() {
+        assert_eq!(
+            release_title_tokens("Moana.2.2024.MULTi.TRUEFRENCH.1080p.WEB-DL.H265-GROUP"),
+            vec!["moana", "2"]
+        );
+        assert_eq!(
+            release_title_tokens("1917 (2019) VFF 1080p BluRay x264"),
+            vec!["1917"]
+        );
+        assert_eq!(
+            release_title_tokens("Breaking.Bad.S02E05.VOSTFR.720p.HDTV.x264-NoTag"),
+            vec!["breaking", "bad"]
+        );
+    }
+
+    #[test]
+    fn test_score() {
+        let exact = score("Moana.2016.MULTi.1080p.BluRay.x264", "Moana 2016");
+        let sequel = score("Moana.2.2024.MULTi.1080p.WEB-DL.H265", "Moana 2016");
+        let bonus = score("Moana.2016.Making.Of.FRENCH.720p", "Moana 2016");
+        assert_eq!(exact, 120);
+        assert!(exact > sequel);
+        assert!(exact > bonus);
+        assert_eq!(
+            score(
+                "Vaiana.La.Legende.Du.Bout.Du.Monde.2016.FRENCH.1080p",
+                "Moana 2016"
+            ),
+            -50
+        );
+        assert_eq!(
+            score(
+                "Vaiana, la légende du bout du monde (2016) FRENCH 1080p",
+                "Vaiana  la legende du bout du monde 2016"
+            ),
+            120
+        );
+    }
+}
@@ -1,8 +1,9 @@
 use crate::auth::login;
+use crate::cache::with_cache_scope;
 use crate::config::Config;
 use crate::domain::get_ygg_domain;
 use crate::rest::client_extractor::MaybeCustomClient;
-use crate::search::{Order, Sort, search};
+use crate::search::{Order, SearchFields, Sort, search};
 use actix_web::{HttpRequest, HttpResponse, get, web};
 use qstring::QString;
 use sysinfo::{Pid, System};
@@ -59,9 +60,11 @@ pub async fn bench_mark(
             };
 
             let start = chrono::Utc::now();
-            let _search = search(
+            // The search cache is bypassed, so that YGG is actually queried
+            let _search = with_cache_scope(true, search(
                 &data.client,
                 "Vaiana",
+                &SearchFields::default(),
                 None,
                 None,
                 None,
@@ -69,7 +72,7 @@ pub async fn bench_mark(
                 Some(Order::Ascending),
                 None,
                 false,
-            ).await;
+            )).await;
             let duration = chrono::Utc::now().signed_duration_since(start);
 
             // Measure CPU after search
@@ -1,4 +1,4 @@
-use crate::categories::CATEGORIES_CACHE;
+use crate::categories::{CATEGORIES_CACHE, with_options};
 use crate::rest::client_extractor::MaybeCustomClient;
 use actix_web::{HttpResponse, get};
 
@@ -10,7 +10,7 @@ pub async fn categories(data: MaybeCustomClient) -> HttpResponse {
         if let Some(cookies) = data.cookies_header {
             response.insert_header(("X-Session-Cookies", cookies));
         }
-        return response.json(cached_categories);
+        return response.json(with_options(cached_categories));
     }
 
     // If cache is empty (shouldn't happen after startup), scrape now
@@ -1,3 +1,4 @@
+use crate::config::Config;
 use crate::ygg_client::YggClient;
 use actix_web::{FromRequest, HttpRequest, dev::Payload, web};
 use std::net::{IpAddr, SocketAddr};
@@ -17,6 +18,54 @@ pub struct MaybeCustomClient {
     pub shared_client: web::Data<YggClient>,
 }
 
+/// Logs in again with the configured account after the session expired, and hands
+/// the new cookies over to the shared client so later requests reuse them.
+pub async fn renew_session(
+    data: &MaybeCustomClient,
+    config: &Config,
+) -> Result<YggClient, Box<dyn std::error::Error>> {
+    info!("Trying to renew session...");
+    let new_client = crate::auth::login(
+        config.username.as_str(),
+        config.password.as_str(),
+        true,
+        config.flaresolverr_url.as_deref(),
+    )
+    .await?;
+
+    // Transfer cookies only in Direct mode
+    if let (Some(new_wreq), Some(shared_wreq)) = (
+        new_client.as_wreq_client(),
+        data.shared_client.as_wreq_client(),
+    ) {
+        let domain = DOMAIN.lock()?;
+        let url = Url::parse(&format!("https://{}/", domain))?;
+        if let Some(cookies) = new_wreq.get_cookies(&url) {
+            shared_wreq.clear_cookies();
+            for cookie_str in cookies.to_str().unwrap_or("").split(';') {
+                let cookie_str = cookie_str.trim();
+                if cookie_str.is_empty() {
+                    continue;
+                }
+                let parts: Vec<&str> = cookie_str.splitn(2, '=').collect();
+                if parts.len() != 2 {
+                    continue;
+                }
+                let cookie = wreq::cookie::CookieBuilder::new(parts[0].trim(), parts[1].trim())
+                    .domain(domain.as_str())
+                    .path("/")
+                    .http_only(true)
+                    .secure(true)
+                    .build();
+                shared_wreq.set_cookie(&url, cookie);
+            }
+        }
+        drop(domain);
+    }
+
+    Ok(new_client)
+}
+
 #[derive(Debug, serde::Deserialize)]
 struct CookieQuery {
     cookie: Option<String>,
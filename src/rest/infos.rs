@@ -1,6 +1,8 @@
+use crate::cache::with_cache_scope;
 use crate::config::Config;
 use crate::rest::client_extractor::MaybeCustomClient;
-use crate::search::{Order, Sort, search};
+use crate::rest::v1::StatusResponse;
+use crate::search::{Order, SearchFields, Sort, search};
 use crate::utils::get_remaining_downloads;
 use crate::{DOMAIN, resolver};
 use actix_web::{HttpResponse, get, web};
@@ -18,21 +20,37 @@ pub async fn health_check() -> HttpResponse {
 
 #[get("/status")]
 pub async fn status_check(data: MaybeCustomClient, config: web::Data<Config>) -> HttpResponse {
+    let status = get_status(&data, &config).await;
+
+    let mut response = HttpResponse::Ok();
+    if let Some(cookies) = data.cookies_header {
+        response.insert_header(("X-Session-Cookies", cookies));
+    }
+    response.json(status)
+}
+
+/// Checks the YGG domain, session, search and parsing, as reported by `/status`.
+/// The probe search always skips the search cache, so it reflects YGG right now.
+pub(crate) async fn get_status(data: &MaybeCustomClient, config: &Config) -> StatusResponse {
     let domain_lock = DOMAIN.lock().unwrap();
     let cloned_guard = domain_lock.clone();
     let domain = cloned_guard.as_str();
     drop(domain_lock);
 
-    let search = search(
-        &data.client,
-        "Vaiana",
-        None,
-        None,
-        None,
-        Some(Sort::Seed),
-        Some(Order::Ascending),
-        None,
-        false,
+    let (search, _) = with_cache_scope(
+        true,
+        search(
+            &data.client,
+            "Vaiana",
+            &SearchFields::default(),
+            None,
+            None,
+            None,
+            Some(Sort::Seed),
+            Some(Order::Ascending),
+            None,
+            false,
+        ),
     )
     .await;
 
@@ -122,21 +140,15 @@ pub async fn status_check(data: MaybeCustomClient, config: web::Data<Config>) ->
         }
     };
 
-    let status = serde_json::json!({
-        "domain": domain,
-        "auth": auth,
-        "search": search_status,
-        "user_info": user_status,
-        "domain_reachability": domain_ping,
-        "domain_dns": dns_lookup,
-        "parsing": parsing,
-        "tmdb_integration": tmdb,
-        "remaining_downloads": remain,
-    });
-
-    let mut response = HttpResponse::Ok();
-    if let Some(cookies) = data.cookies_header {
-        response.insert_header(("X-Session-Cookies", cookies));
+    StatusResponse {
+        domain: domain.to_string(),
+        auth: auth.to_string(),
+        search: search_status.to_string(),
+        user_info: user_status.to_string(),
+        domain_reachability: domain_ping.to_string(),
+        domain_dns: dns_lookup.to_string(),
+        parsing: parsing.to_string(),
+        tmdb_integration: tmdb.to_string(),
+        remaining_downloads: remain,
     }
-    response.json(status)
 }
@@ -0,0 +1,77 @@
+use crate::config::Config;
+use crate::jobs::JobStatus;
+use crate::rest::client_extractor::MaybeCustomClient;
+use actix_web::{HttpRequest, HttpResponse, get, post, web};
+use qstring::QString;
+
+/// Custom session of a request, from its `cookie` parameter.
+fn request_owner(req_data: &HttpRequest) -> Option<String> {
+    let qs = QString::from(req_data.query_string());
+    qs.get("cookie")
+        .filter(|cookies| !cookies.is_empty())
+        .map(crate::jobs::session_owner)
+}
+
+#[post("/torrent/{id:[0-9]+}/prepare")]
+pub async fn prepare_torrent(
+    data: MaybeCustomClient,
+    config: web::Data<Config>,
+    req_data: HttpRequest,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let id = req_data.match_info().get("id").unwrap();
+    let id = id.parse::<usize>()?;
+
+    let owner = match data.is_custom {
+        true => request_owner(&req_data),
+        false => None,
+    };
+    let Some(job) = crate::jobs::prepare(
+        data.client.clone(),
+        id,
+        config.turbo_enabled.unwrap_or(false),
+        owner,
+    )?
+    else {
+        return Ok(HttpResponse::TooManyRequests()
+            .insert_header(("Retry-After", crate::jobs::DOWNLOAD_WAIT.to_string()))
+            .body("Too many download jobs, retry later"));
+    };
+
+    let mut response = HttpResponse::Accepted();
+    response.insert_header(("Location", format!("/jobs/{}", job.id)));
+    if let Some(cookies) = data.cookies_header {
+        response.insert_header(("X-Session-Cookies", cookies));
+    }
+    Ok(response.json(job.to_json()))
+}
+
+#[get("/jobs/{id}")]
+pub async fn job_status(req_data: HttpRequest) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let id = req_data.match_info().get("id").unwrap();
+    match crate::jobs::get(id, request_owner(&req_data).as_deref()) {
+        Some(job) => Ok(HttpResponse::Ok().json(job.to_json())),
+        None => Ok(HttpResponse::NotFound().body("Job not found")),
+    }
+}
+
+#[get("/jobs/{id}/file")]
+pub async fn job_file(req_data: HttpRequest) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let id = req_data.match_info().get("id").unwrap();
+    let Some(job) = crate::jobs::get(id, request_owner(&req_data).as_deref()) else {
+        return Ok(HttpResponse::NotFound().body("Job not found"));
+    };
+
+    match (job.status, job.file.clone()) {
+        (JobStatus::Ready, Some(file)) => Ok(HttpResponse::Ok()
+            .content_type("application/x-bittorrent")
+            .append_header((
+                "Content-Disposition",
+                format!("attachment; filename=\"{}.torrent\"", job.torrent_id),
+            ))
+            .body(file)),
+        (JobStatus::Failed, _) => Ok(HttpResponse::InternalServerError().json(job.to_json())),
+        _ => Ok(HttpResponse::Accepted()
+            .insert_header(("Retry-After", job.remaining().max(1).to_string()))
+            .json(job.to_json())),
+    }
+}
@@ -3,9 +3,13 @@ use crate::rest::bench::*;
 use crate::rest::categories::*;
 use crate::rest::homepage::*;
 use crate::rest::infos::*;
+use crate::rest::jobs::*;
 use crate::rest::remain::*;
+use crate::rest::rss::*;
 use crate::rest::search::*;
+use crate::rest::top::*;
 use crate::rest::torrent::*;
+use crate::rest::torznab::*;
 use crate::rest::user::*;
 use actix_web::web;
 
@@ -15,20 +19,38 @@ mod categories;
 pub mod client_extractor;
 mod homepage;
 mod infos;
+mod jobs;
 mod remain;
+mod rss;
 pub mod search;
+mod stream;
+mod top;
 mod torrent;
+mod torznab;
 mod user;
+pub mod v1;
 
 pub fn config_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(categories)
         .service(ygg_search)
         .service(download_torrent)
+        .service(send_torrent)
+        .service(torrent_info)
+        .service(torrent_meta)
+        .service(torrent_magnet)
+        .service(torrent_nfo)
         .service(get_user_info)
         .service(health_check)
         .service(status_check)
         .service(index)
         .service(remaining_downloads_status)
         .service(auth)
-        .service(bench_mark);
+        .service(bench_mark)
+        .service(torznab_api)
+        .service(rss_feed)
+        .service(top_torrents)
+        .service(prepare_torrent)
+        .service(job_status)
+        .service(job_file)
+        .configure(v1::config_v1_routes);
 }
@@ -0,0 +1,85 @@
+use crate::cache::with_cache_scope;
+use crate::categories::CATEGORIES_CACHE;
+use crate::config::Config;
+use crate::rest::client_extractor::MaybeCustomClient;
+use crate::rest::search::{SearchParams, load_requested_options, search_torrents};
+use crate::rss::{feed_title, feed_xml};
+use crate::search::{Order, Sort};
+use actix_web::{HttpRequest, HttpResponse, get, web};
+use qstring::QString;
+
+#[get("/rss")]
+pub async fn rss_feed(
+    data: MaybeCustomClient,
+    config: web::Data<Config>,
+    req_data: HttpRequest,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let query = req_data.query_string();
+    debug!("Received RSS query: {}", query);
+    let qs = QString::from(query);
+
+    let api_key = config.rss_api_key.as_deref().filter(|k| !k.is_empty());
+    if api_key.is_some_and(|key| !keys_match(qs.get("apikey").unwrap_or(""), key)) {
+        return Ok(HttpResponse::Unauthorized().body("Invalid API key"));
+    }
+
+    load_requested_options(&data, &config, &qs).await;
+    let mut params = match SearchParams::from_query(&qs) {
+        Ok(params) => params,
+        Err(e) => return Ok(HttpResponse::BadRequest().body(e)),
+    };
+    // Latest torrents first, unless asked otherwise
+    params.sort = params.sort.or(Some(Sort::PublishDate));
+    params.order = params.order.or(Some(Order::Descending));
+
+    let categories = CATEGORIES_CACHE.get().map(Vec::as_slice).unwrap_or(&[]);
+    let title = feed_title(
+        &params.name,
+        params.category,
+        params.sub_category,
+        categories,
+    );
+
+    let no_cache = qs.get("no_cache").map(|s| s == "true").unwrap_or(false);
+    let (torrents, cache_status) =
+        with_cache_scope(no_cache, search_torrents(&data, &config, params)).await;
+    let torrents = torrents?;
+
+    let connection_info = req_data.connection_info();
+    let base_url = format!("{}://{}", connection_info.scheme(), connection_info.host());
+    let feed_url = format!("{}{}", base_url, req_data.uri());
+
+    let mut response = HttpResponse::Ok();
+    response.content_type("application/rss+xml; charset=utf-8");
+    response.insert_header(("X-Cache", cache_status.as_str()));
+    if let Some(cookies) = data.cookies_header {
+        response.insert_header(("X-Session-Cookies", cookies));
+    }
+    Ok(response.body(feed_xml(
+        &torrents, categories, &title, &base_url, &feed_url,
+    )))
+}
+
+/// Compares API keys in constant time, so the time taken doesn't tell how much
+/// of a guess is right.
+fn keys_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_match() {
+        assert!(keys_match("secret", "secret"));
+        assert!(!keys_match("secreT", "secret"));
+        assert!(!keys_match("secret1", "secret"));
+        assert!(!keys_match("", "secret"));
+    }
+}
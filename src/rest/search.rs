@@ -1,25 +1,44 @@
+use crate::cache::with_cache_scope;
+use crate::categories::{
+    category_options, is_ygg_category, merge_sub_categories, resolve_category, resolve_options,
+    sub_category_options, ygg_categories_from_newznab,
+};
 use crate::config::Config;
 use crate::dbs::DbQueryType::*;
+use crate::dbs::MediaType;
+use crate::episode::EpisodeQuery;
+use crate::explain::{self, DatabaseLookup, QueryReport, with_explain_scope};
+use crate::filters::ResultFilters;
 use crate::parser::Torrent;
-use crate::rest::client_extractor::MaybeCustomClient;
-use crate::search::{Order, Sort, search};
+use crate::relevance::score_torrents;
+use crate::rest::client_extractor::{MaybeCustomClient, renew_session};
+use crate::rest::stream::{stream_search, wants_stream};
+use crate::search::{Order, SearchFields, SearchMode, Sort, search_window};
 use crate::ygg_client::YggClient;
 use actix_web::{HttpRequest, HttpResponse, get, web};
 use futures::future::join_all;
 use qstring::QString;
-use serde_json::Value;
+use serde_json::{Value, json};
 use std::collections::HashSet;
+use std::time::Duration;
+use utoipa::openapi::schema::Type;
+
+/// Result of a YGG search of a batch, with how long it took.
+type TimedResult = (Result<Vec<Torrent>, Box<dyn std::error::Error>>, Duration);
 
 async fn batch_best_search(
     client: &YggClient,
     queries: Vec<String>,
+    fields: &SearchFields,
     offset: Option<usize>,
+    limit: Option<usize>,
     category: Option<usize>,
     sub_category: Option<usize>,
     sort: Option<Sort>,
     order: Option<Order>,
     ban_words: Option<Vec<String>>,
     quote_search: bool,
+    filters: &ResultFilters,
     config: &Config,
 ) -> Result<Vec<Torrent>, Box<dyn std::error::Error>> {
     debug!("Starting parallel search for {} queries", queries.len());
@@ -28,28 +47,35 @@ async fn batch_best_search(
         debug!("Query #{}: {}", idx + 1, query);
     }
 
+    let (query_offset, query_limit) = batch_window(offset, limit);
     let search_futures: Vec<_> = queries
         .iter()
         .map(|query| {
-            search(
+            explain::timed(search_window(
                 client,
                 query.as_str(),
-                offset,
+                fields,
+                query_offset,
+                query_limit,
                 category,
                 sub_category,
                 sort,
                 order,
                 ban_words.clone(),
                 quote_search,
-            )
+            ))
         })
         .collect();
 
-    let results = join_all(search_futures).await;
+    let results = filter_results(join_all(search_futures).await, filters);
+    explain_searches(
+        queries.iter().map(|query| (query.as_str(), category)),
+        &results,
+    );
 
     let mut collected_torrents: HashSet<Torrent> = HashSet::new();
 
-    for (idx, result) in results.into_iter().enumerate() {
+    for (idx, (result, _)) in results.into_iter().enumerate() {
         match result {
             Ok(mut torrents) => {
                 if torrents.len() > 5 {
@@ -59,7 +85,19 @@ async fn batch_best_search(
                         idx + 1,
                         queries[idx]
                     );
-                    Torrent::sort(&mut torrents, sort, order);
+                    // Every query already ran, the results of the others are dropped
+                    explain::record(|explain| {
+                        for report in explain.searches.iter_mut() {
+                            report.decision = Some("discarded");
+                        }
+                        explain.searches[idx].decision = Some("returned");
+                        explain.outcome = Some(format!(
+                            "Query #{} found more than 5 results, returned alone",
+                            idx + 1
+                        ));
+                    });
+                    sort_results(&mut torrents, &queries, sort, order);
+                    apply_window(&mut torrents, offset, limit);
                     return Ok(torrents);
                 } else if torrents.len() >= 5 {
                     debug!(
@@ -68,6 +106,7 @@ async fn batch_best_search(
                         idx + 1,
                         queries[idx]
                     );
+                    explain::record(|explain| explain.searches[idx].decision = Some("merged"));
                     torrents.into_iter().for_each(|t| {
                         collected_torrents.insert(t);
                     });
@@ -78,6 +117,7 @@ async fn batch_best_search(
                         idx + 1,
                         queries[idx]
                     );
+                    explain::record(|explain| explain.searches[idx].decision = Some("fallback"));
                     torrents.into_iter().for_each(|t| {
                         collected_torrents.insert(t);
                     });
@@ -88,11 +128,13 @@ async fn batch_best_search(
                         queries[idx],
                         torrents.len()
                     );
+                    explain::record(|explain| explain.searches[idx].decision = Some("ignored"));
                 }
             }
             Err(e) => {
                 if e.to_string().contains("Session expired") {
                     info!("Session expired during TMDB search, attempting renewal...");
+                    explain::record(|explain| explain.session_renewed = true);
                     let new_client = crate::auth::login(
                         config.username.as_str(),
                         config.password.as_str(),
@@ -104,13 +146,16 @@ async fn batch_best_search(
                     return Box::pin(batch_best_search(
                         &new_client,
                         queries,
+                        fields,
                         offset,
+                        limit,
                         category,
                         sub_category,
                         sort,
                         order,
                         ban_words,
                         quote_search,
+                        filters,
                         config,
                     ))
                     .await;
@@ -131,25 +176,42 @@ async fn batch_best_search(
             "Returning {} merged torrents from multiple queries",
             collected_torrents.len()
         );
+        explain::record(|explain| {
+            let merged = explain
+                .searches
+                .iter()
+                .filter(|report| matches!(report.decision, Some("merged" | "fallback")))
+                .count();
+            explain.outcome = Some(format!(
+                "No query found more than 5 results, merged the {} results of {} queries",
+                collected_torrents.len(),
+                merged
+            ))
+        });
         let mut torrents: Vec<Torrent> = collected_torrents.into_iter().collect();
-        Torrent::sort(&mut torrents, sort, order);
+        sort_results(&mut torrents, &queries, sort, order);
+        apply_window(&mut torrents, offset, limit);
         return Ok(torrents);
     }
 
     debug!("All TMDB queries returned empty results");
+    explain::record(|explain| explain.outcome = Some("All queries returned empty results".into()));
     Ok(vec![])
 }
 
 async fn batch_category_search(
     client: &YggClient,
     name: &str,
+    fields: &SearchFields,
     offset: Option<usize>,
+    limit: Option<usize>,
     cats_list: Vec<usize>,
     sub_category: Option<usize>,
     sort: Option<Sort>,
     order: Option<Order>,
     ban_words: Option<Vec<String>>,
     quote_search: bool,
+    filters: &ResultFilters,
     config: &Config,
 ) -> Result<Vec<Torrent>, Box<dyn std::error::Error>> {
     debug!(
@@ -157,28 +219,32 @@ async fn batch_category_search(
         cats_list.len()
     );
 
+    let (query_offset, query_limit) = batch_window(offset, limit);
     let search_futures: Vec<_> = cats_list
         .iter()
         .map(|cat| {
-            search(
+            explain::timed(search_window(
                 client,
                 name,
-                offset,
+                fields,
+                query_offset,
+                query_limit,
                 Some(*cat),
                 sub_category,
                 sort,
                 order,
                 ban_words.clone(),
                 quote_search,
-            )
+            ))
         })
         .collect();
 
-    let results = join_all(search_futures).await;
+    let results = filter_results(join_all(search_futures).await, filters);
+    explain_searches(cats_list.iter().map(|cat| (name, Some(*cat))), &results);
 
     let mut collected_torrents: HashSet<Torrent> = HashSet::new();
 
-    for (idx, result) in results.into_iter().enumerate() {
+    for (idx, (result, _)) in results.into_iter().enumerate() {
         match result {
             Ok(torrents) => {
                 debug!(
@@ -193,6 +259,7 @@ async fn batch_category_search(
             Err(e) => {
                 if e.to_string().contains("Session expired") {
                     info!("Session expired during category search, attempting renewal...");
+                    explain::record(|explain| explain.session_renewed = true);
                     let new_client = crate::auth::login(
                         config.username.as_str(),
                         config.password.as_str(),
@@ -204,13 +271,16 @@ async fn batch_category_search(
                     return Box::pin(batch_category_search(
                         &new_client,
                         name,
+                        fields,
                         offset,
+                        limit,
                         cats_list,
                         sub_category,
                         sort,
                         order,
                         ban_words,
                         quote_search,
+                        filters,
                         config,
                     ))
                     .await;
@@ -226,159 +296,781 @@ async fn batch_category_search(
         collected_torrents.len(),
         cats_list.len()
     );
+    explain::record(|explain| {
+        explain.outcome = Some(format!(
+            "Merged {} results from {} categories",
+            collected_torrents.len(),
+            cats_list.len()
+        ))
+    });
     let mut torrents: Vec<Torrent> = collected_torrents.into_iter().collect();
-    Torrent::sort(&mut torrents, sort, order);
+    sort_results(&mut torrents, &[name.to_string()], sort, order);
+    apply_window(&mut torrents, offset, limit);
     Ok(torrents)
 }
 
-#[get("/search")]
-pub async fn ygg_search(
-    data: MaybeCustomClient,
-    config: web::Data<Config>,
-    req_data: HttpRequest,
-) -> Result<HttpResponse, Box<dyn std::error::Error>> {
-    let query = req_data.query_string();
-    debug!("Received query: {}", query);
-    let qs = QString::from(query);
-    let name = qs.get("name").or(qs.get("q")).unwrap_or("");
-    let offset = qs.get("offset").and_then(|s| s.parse::<usize>().ok());
-    let category = qs.get("category").and_then(|s| s.parse::<usize>().ok());
-    let sub_category = qs.get("sub_category").and_then(|s| s.parse::<usize>().ok());
-    let mut sort = qs.get("sort").and_then(|s| s.parse::<Sort>().ok());
-    let mut order = qs.get("order").and_then(|s| s.parse::<Order>().ok());
-    let cats = qs.get("categories");
-    let connarr = qs.get("connarr");
-    let quote_search = qs.get("quote_search").map(|s| s == "true").unwrap_or(false);
-
-    debug!("Prowlarr/Jackett detected");
-
-    let ban_words = qs.get("ban_words").and_then(|s| {
-        let v: Vec<String> = s
-            .split(',')
-            .map(|word| word.trim().to_string())
-            .filter(|w| !w.is_empty())
-            .collect();
-        if v.is_empty() { None } else { Some(v) }
-    });
+/// Searches the season/episode variants of the titles one variant at a time, and
+/// stops at the first variant with matching releases. Several sub-categories of
+/// the same category are searched through their parent, once.
+async fn batch_episode_search(
+    data: &MaybeCustomClient,
+    client: &YggClient,
+    names: &[String],
+    fields: &SearchFields,
+    episode_query: EpisodeQuery,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    category: Option<usize>,
+    categories: Option<Vec<usize>>,
+    sub_category: Option<usize>,
+    sort: Option<Sort>,
+    order: Option<Order>,
+    ban_words: Option<Vec<String>>,
+    quote_search: bool,
+    filters: &ResultFilters,
+    config: &Config,
+) -> Result<Vec<Torrent>, Box<dyn std::error::Error>> {
+    let variants: Vec<Vec<String>> = names
+        .iter()
+        .map(|name| episode_query.queries(name))
+        .collect();
+    let cats_list: Vec<Option<usize>> = match (category, &categories) {
+        (None, Some(cats)) => merge_sub_categories(cats).into_iter().map(Some).collect(),
+        _ => vec![category],
+    };
+
+    let (query_offset, query_limit) = batch_window(offset, limit);
+    let mut reports: Vec<QueryReport> = Vec::new();
+    let mut collected_torrents: HashSet<Torrent> = HashSet::new();
 
-    let mut categories_list = if let Some(cats) = cats {
-        let decoded = urlencoding::decode(cats).unwrap_or(std::borrow::Cow::Borrowed(cats));
-        let parsed: Vec<usize> = decoded
-            .split(',')
-            .filter_map(|s| s.trim().parse::<usize>().ok())
+    for variant in 0..variants.first().map_or(0, Vec::len) {
+        let searches: Vec<(&String, Option<usize>)> = variants
+            .iter()
+            .map(|queries| &queries[variant])
+            .flat_map(|query| cats_list.iter().map(move |cat| (query, *cat)))
             .collect();
-        if !parsed.is_empty() {
-            Some(parsed)
-        } else {
-            None
-        }
-    } else {
-        None
-    };
 
-    if categories_list.is_some() && connarr.is_some() {
-        if categories_list.as_ref().unwrap().len() > 2 {
-            categories_list = None;
-        }
-    }
+        debug!(
+            "Starting parallel episode search for {:?}, variant #{} ({} searches)",
+            episode_query,
+            variant + 1,
+            searches.len()
+        );
 
-    if config.tmdb_token.is_some() && (qs.get("tmdbid").is_some() || qs.get("imdbid").is_some()) {
-        let db_search = if let Some(id) = qs.get("tmdbid") {
-            Some((id, TMDB, "TMDB"))
-        } else if let Some(id) = qs.get("imdbid") {
-            Some((id, IMDB, "IMDB"))
-        } else {
-            None
-        };
+        let search_futures: Vec<_> = searches
+            .iter()
+            .map(|(query, cat)| {
+                explain::timed(search_window(
+                    client,
+                    query.as_str(),
+                    fields,
+                    query_offset,
+                    query_limit,
+                    *cat,
+                    sub_category,
+                    sort,
+                    order,
+                    ban_words.clone(),
+                    quote_search,
+                ))
+            })
+            .collect();
 
-        return if let Some((id, db_type, db_name)) = db_search {
-            match crate::dbs::get_queries(
-                id.to_string(),
-                &config.tmdb_token.clone().unwrap(),
-                db_type,
-            )
-            .await
-            {
-                Ok(queries) => {
-                    debug!(
-                        "Got {} queries from {} for ID {}",
-                        queries.len(),
-                        db_name,
-                        id
-                    );
-                    let results = batch_best_search(
-                        &data.client,
-                        queries,
-                        offset,
-                        category,
-                        sub_category,
-                        sort,
-                        order,
-                        ban_words.clone(),
-                        quote_search,
-                        &config,
-                    )
-                    .await?;
+        let results = filter_results(join_all(search_futures).await, filters);
+        reports.extend(query_reports(
+            searches.iter().map(|(query, cat)| (query.as_str(), *cat)),
+            &results,
+        ));
 
-                    if !results.is_empty() {
-                        info!("{} torrents found via {} search", results.len(), db_name);
-                        let torrent_json: Vec<Value> =
-                            results.into_iter().map(|t| t.to_json()).collect();
-                        let mut response = HttpResponse::Ok();
-                        if let Some(cookies) = data.cookies_header {
-                            response.insert_header(("X-Session-Cookies", cookies));
-                        }
-                        return Ok(response.json(torrent_json));
-                    }
+        for (idx, (result, _)) in results.into_iter().enumerate() {
+            match result {
+                Ok(torrents) => {
                     debug!(
-                        "{} search returned no results, falling back to regular search",
-                        db_name
+                        "Query {} (category {:?}) returned {} results",
+                        searches[idx].0,
+                        searches[idx].1,
+                        torrents.len()
                     );
-                    let mut response = HttpResponse::Ok();
-                    if let Some(cookies) = data.cookies_header {
-                        response.insert_header(("X-Session-Cookies", cookies));
-                    }
-                    Ok(response.json(Vec::<Value>::new()))
+                    torrents
+                        .into_iter()
+                        .filter(|t| episode_query.matches(&t.name))
+                        .for_each(|t| {
+                            collected_torrents.insert(t);
+                        });
                 }
                 Err(e) => {
-                    warn!("Failed to get {} queries for ID {}: {}", db_name, id, e);
-                    let mut response = HttpResponse::Ok();
-                    if let Some(cookies) = data.cookies_header {
-                        response.insert_header(("X-Session-Cookies", cookies));
+                    // If session expired and NOT using custom cookies, try to renew
+                    if e.to_string().contains("Session expired") && !data.is_custom {
+                        explain::record(|explain| explain.session_renewed = true);
+                        let new_client = renew_session(data, config).await?;
+
+                        return Box::pin(batch_episode_search(
+                            data,
+                            &new_client,
+                            names,
+                            fields,
+                            episode_query,
+                            offset,
+                            limit,
+                            category,
+                            categories,
+                            sub_category,
+                            sort,
+                            order,
+                            ban_words,
+                            quote_search,
+                            filters,
+                            config,
+                        ))
+                        .await;
+                    } else {
+                        warn!("Search failed for query {}: {}", searches[idx].0, e);
                     }
-                    Ok(response.json(Vec::<Value>::new()))
                 }
             }
-        } else {
-            warn!("No valid database ID provided for DB search");
-            let mut response = HttpResponse::Ok();
-            if let Some(cookies) = data.cookies_header {
-                response.insert_header(("X-Session-Cookies", cookies));
-            }
-            Ok(response.json(Vec::<Value>::new()))
+        }
+
+        if !collected_torrents.is_empty() {
+            debug!(
+                "Variant #{} found matching releases, skipping the next ones",
+                variant + 1
+            );
+            break;
+        }
+    }
+
+    debug!(
+        "Returning {} torrents matching {:?}",
+        collected_torrents.len(),
+        episode_query
+    );
+    explain::record(|explain| {
+        explain.searches = reports;
+        explain.outcome = Some(format!(
+            "Merged {} results matching {:?}",
+            collected_torrents.len(),
+            episode_query
+        ))
+    });
+    let mut torrents: Vec<Torrent> = collected_torrents.into_iter().collect();
+    sort_results(&mut torrents, names, sort, order);
+    apply_window(&mut torrents, offset, limit);
+    Ok(torrents)
+}
+
+/// Applies the result filters to the searches of a batch.
+fn filter_results(results: Vec<TimedResult>, filters: &ResultFilters) -> Vec<TimedResult> {
+    results
+        .into_iter()
+        .map(|(result, elapsed)| (result.map(|torrents| filters.apply(torrents)), elapsed))
+        .collect()
+}
+
+/// Reports the `(query, category)` searches of a batch to the explain diagnostics.
+fn explain_searches<'a>(
+    searches: impl Iterator<Item = (&'a str, Option<usize>)>,
+    results: &[TimedResult],
+) {
+    explain::record(|explain| explain.searches = query_reports(searches, results));
+}
+
+/// Explain reports of the `(query, category)` searches of a batch.
+fn query_reports<'a>(
+    searches: impl Iterator<Item = (&'a str, Option<usize>)>,
+    results: &[TimedResult],
+) -> Vec<QueryReport> {
+    searches
+        .zip(results)
+        .map(|((query, category), (result, elapsed))| {
+            let count = result.as_ref().map(Vec::len).map_err(|e| e.to_string());
+            QueryReport::new(query, category, count, *elapsed)
+        })
+        .collect()
+}
+
+/// Window fetched by each search of a batch. With a limit, the results are merged
+/// and sorted before the requested window is cut, so every search returns its
+/// first `offset + limit` results.
+fn batch_window(offset: Option<usize>, limit: Option<usize>) -> (Option<usize>, Option<usize>) {
+    match limit {
+        Some(limit) => (None, Some(offset.unwrap_or(0).saturating_add(limit))),
+        None => (offset, None),
+    }
+}
+
+/// Cuts the requested window out of the merged and sorted results of a batch.
+fn apply_window(torrents: &mut Vec<Torrent>, offset: Option<usize>, limit: Option<usize>) {
+    let Some(limit) = limit else {
+        return;
+    };
+    torrents.drain(..offset.unwrap_or(0).min(torrents.len()));
+    torrents.truncate(limit);
+}
+
+/// Sorts merged results. With `Sort::Relevance`, they are first scored against the
+/// searched titles.
+fn sort_results(
+    torrents: &mut Vec<Torrent>,
+    titles: &[String],
+    sort: Option<Sort>,
+    order: Option<Order>,
+) {
+    if matches!(sort, Some(Sort::Relevance)) {
+        score_torrents(torrents, titles);
+    }
+    Torrent::sort(torrents, sort, order);
+}
+
+/// Query parameters read by `SearchParams::from_query`, as `(name, type,
+/// description)`. They document the endpoints taking them, like `/api/v1/search`.
+pub(crate) const SEARCH_QUERY_PARAMS: &[(&str, Type, &str)] = &[
+    ("q", Type::String, "Search terms (`name` is accepted too)"),
+    ("uploader", Type::String, "Uploader name"),
+    (
+        "description",
+        Type::String,
+        "Term searched in the description",
+    ),
+    ("file", Type::String, "Term searched in file names"),
+    (
+        "offset",
+        Type::Integer,
+        "Offset of the first result (with `limit`), or YGG page offset",
+    ),
+    ("limit", Type::Integer, "Number of results (max 500)"),
+    (
+        "category",
+        Type::String,
+        "Category id, name or slug (`film-video`, `serie-tv`, `film-video/animation`)",
+    ),
+    (
+        "sub_category",
+        Type::String,
+        "Sub-category id, name or slug",
+    ),
+    (
+        "categories",
+        Type::String,
+        "Comma separated category ids, names or slugs",
+    ),
+    (
+        "sort",
+        Type::String,
+        "`name`, `seed`, `comments`, `publish_date`, `completed`, `leech` or `relevance`",
+    ),
+    ("order", Type::String, "`asc` or `desc`"),
+    ("imdbid", Type::String, "IMDB id (`tt1234567`)"),
+    ("tmdbid", Type::String, "TMDB id"),
+    ("tvdbid", Type::String, "TVDB id"),
+    (
+        "mode",
+        Type::String,
+        "`search`, `tvsearch`, `movie`, `music` or `book`",
+    ),
+    ("season", Type::Integer, "Season number"),
+    ("ep", Type::Integer, "Episode number, with `season`"),
+    (
+        "ban_words",
+        Type::String,
+        "Comma separated words to exclude",
+    ),
+    ("quote_search", Type::Boolean, "Quote each searched word"),
+    ("min_seeders", Type::Integer, "Minimum number of seeders"),
+    (
+        "min_size",
+        Type::String,
+        "Bytes, or with a unit (`700MB`, `1.5Go`)",
+    ),
+    (
+        "max_size",
+        Type::String,
+        "Bytes, or with a unit (`700MB`, `1.5Go`)",
+    ),
+    (
+        "max_age",
+        Type::String,
+        "Seconds, or with a unit (`12h`, `7d`)",
+    ),
+    (
+        "since",
+        Type::String,
+        "Unix timestamp, `YYYY-MM-DD` or RFC 3339 date",
+    ),
+    (
+        "include",
+        Type::String,
+        "Case insensitive regex the name must match",
+    ),
+    (
+        "exclude",
+        Type::String,
+        "Case insensitive regex the name must not match",
+    ),
+    (
+        "resolution",
+        Type::String,
+        "Accepted resolutions (`2160p,1080p`)",
+    ),
+    (
+        "source",
+        Type::String,
+        "Accepted sources (`WEB-DL`, `BluRay`...)",
+    ),
+    (
+        "video_codec",
+        Type::String,
+        "Accepted video codecs (`H265`...)",
+    ),
+    (
+        "audio_codec",
+        Type::String,
+        "Accepted audio codecs (`EAC3`...)",
+    ),
+    (
+        "hdr",
+        Type::String,
+        "Accepted HDR formats, or `true`/`false`",
+    ),
+    (
+        "language",
+        Type::String,
+        "Accepted languages (`MULTi`, `VFF`...)",
+    ),
+    ("group", Type::String, "Accepted release groups"),
+    ("year", Type::String, "Accepted years"),
+    ("no_cache", Type::Boolean, "Skip the search cache"),
+];
+
+/// Search parameters shared by the JSON `/search` endpoint and the Torznab API.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SearchParams {
+    pub name: String,
+    pub fields: SearchFields,
+    pub offset: Option<usize>,
+    /// When set, `offset` is a result offset and `limit` results are gathered
+    /// over as many YGG pages as needed.
+    pub limit: Option<usize>,
+    pub category: Option<usize>,
+    pub sub_category: Option<usize>,
+    pub sort: Option<Sort>,
+    pub order: Option<Order>,
+    pub categories: Option<Vec<usize>>,
+    pub connarr: bool,
+    pub quote_search: bool,
+    pub ban_words: Option<Vec<String>>,
+    pub filters: ResultFilters,
+    pub tmdbid: Option<String>,
+    pub imdbid: Option<String>,
+    pub tvdbid: Option<String>,
+    pub mode: SearchMode,
+    pub season: Option<u32>,
+    pub episode: Option<u32>,
+}
+
+impl SearchParams {
+    pub(crate) fn from_query(qs: &QString) -> Result<Self, String> {
+        let name = qs.get("name").or(qs.get("q")).unwrap_or("").to_string();
+        let offset = qs.get("offset").and_then(|s| s.parse::<usize>().ok());
+        let limit = qs.get("limit").and_then(|s| s.parse::<usize>().ok());
+        let mut category = non_empty(qs.get("category"))
+            .map(|s| resolve_category(&s, false).and_then(|id| known_category(id, true)))
+            .transpose()?;
+        let sub_category = non_empty(qs.get("sub_category"))
+            .map(|s| resolve_category(&s, true).and_then(|id| known_category(id, false)))
+            .transpose()?;
+        let fields = SearchFields {
+            uploader: non_empty(qs.get("uploader")),
+            description: non_empty(qs.get("description")),
+            file: non_empty(qs.get("file")),
+            options: parse_options(qs, sub_category.or(category))?,
         };
+        let sort = non_empty(qs.get("sort"))
+            .map(|s| s.parse::<Sort>())
+            .transpose()?;
+        let order = non_empty(qs.get("order"))
+            .map(|s| s.parse::<Order>())
+            .transpose()?;
+        let connarr = qs.get("connarr").is_some();
+        let quote_search = qs.get("quote_search").map(|s| s == "true").unwrap_or(false);
+
+        let ban_words = qs.get("ban_words").and_then(|s| {
+            let v: Vec<String> = s
+                .split(',')
+                .map(|word| word.trim().to_string())
+                .filter(|w| !w.is_empty())
+                .collect();
+            if v.is_empty() { None } else { Some(v) }
+        });
+
+        let filters = ResultFilters::from_query(qs)?;
+
+        let tmdbid = non_empty(qs.get("tmdbid"));
+        let imdbid = non_empty(qs.get("imdbid"));
+        let tvdbid = non_empty(qs.get("tvdbid"));
+        let season = qs.get("season").and_then(|s| s.parse::<u32>().ok());
+        let episode = qs.get("ep").and_then(|s| s.parse::<u32>().ok());
+
+        // Explicit mode (Cardigann `.Query.Type`), otherwise guessed from the TV parameters
+        let mode = qs
+            .get("mode")
+            .and_then(|s| s.parse::<SearchMode>().ok())
+            .unwrap_or(
+                match season.is_some() || episode.is_some() || tvdbid.is_some() {
+                    true => SearchMode::Tv,
+                    false => SearchMode::Search,
+                },
+            );
+
+        let mut categories = qs
+            .get("categories")
+            .map(parse_category_list)
+            .transpose()?
+            .flatten();
+
+        if connarr && categories.as_ref().is_some_and(|cats| cats.len() > 2) {
+            categories = None;
+        }
+
+        // A Newznab category (2000, 5000...) searches its YGG sub-categories
+        if let Some(expanded) = category.and_then(ygg_categories_from_newznab) {
+            category = None;
+            categories = Some([categories.unwrap_or_default(), expanded].concat());
+        }
+        let categories = categories.map(expand_newznab_categories);
+
+        Ok(SearchParams {
+            name,
+            fields,
+            offset,
+            limit,
+            category,
+            sub_category,
+            sort,
+            order,
+            categories,
+            connarr,
+            quote_search,
+            ban_words,
+            filters,
+            tmdbid,
+            imdbid,
+            tvdbid,
+            mode,
+            season,
+            episode,
+        })
+    }
+}
+
+/// `option_*` filters of a request, as `(name, values)` pairs.
+fn requested_options(qs: &QString) -> Vec<(String, String)> {
+    qs.to_pairs()
+        .into_iter()
+        .filter(|(key, value)| key.starts_with("option_") && !value.is_empty())
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Resolves the `option_*` filters against the options of the searched sub-category.
+fn parse_options(
+    qs: &QString,
+    sub_category: Option<usize>,
+) -> Result<Vec<(String, String)>, String> {
+    let requested = requested_options(qs);
+    if requested.is_empty() {
+        return Ok(vec![]);
+    }
+    let options = sub_category
+        .and_then(sub_category_options)
+        .ok_or("option_* filters need a sub_category whose search options could be loaded")?;
+    resolve_options(&options, &requested)
+}
+
+/// Loads the search options of the sub-category filtered with `option_*`, so that
+/// `SearchParams::from_query` can resolve them. Failures are logged, the filters
+/// are then rejected by `from_query`.
+pub(crate) async fn load_requested_options(
+    data: &MaybeCustomClient,
+    config: &Config,
+    qs: &QString,
+) {
+    if requested_options(qs).is_empty() {
+        return;
+    }
+    let sub_category = match non_empty(qs.get("sub_category")) {
+        Some(sub_category) => resolve_category(&sub_category, true).ok(),
+        None => non_empty(qs.get("category")).and_then(|c| resolve_category(&c, false).ok()),
+    };
+    let Some(sub_category) = sub_category else {
+        return;
+    };
+
+    let options = match category_options(&data.client, sub_category).await {
+        Err(e) if e.to_string().contains("Session expired") && !data.is_custom => {
+            match renew_session(data, config).await {
+                Ok(new_client) => category_options(&new_client, sub_category).await,
+                Err(e) => Err(e),
+            }
+        }
+        options => options,
+    };
+    if let Err(e) = options {
+        warn!(
+            "Failed to get search options of sub-category {}: {}",
+            sub_category, e
+        );
+    }
+}
+
+/// Cardigann sends every input, even when the value is empty.
+pub(crate) fn non_empty(value: Option<&str>) -> Option<String> {
+    value.filter(|v| !v.is_empty()).map(|v| v.to_string())
+}
+
+/// Parses a comma separated (and possibly url-encoded) list of numeric ids.
+pub(crate) fn parse_id_list(list: &str) -> Option<Vec<usize>> {
+    let decoded = urlencoding::decode(list).unwrap_or(std::borrow::Cow::Borrowed(list));
+    let parsed: Vec<usize> = decoded
+        .split(',')
+        .filter_map(|s| s.trim().parse::<usize>().ok())
+        .collect();
+    if !parsed.is_empty() {
+        Some(parsed)
     } else {
-        if qs.get("tmdbid").is_some() || qs.get("imdbid").is_some() {
-            warn!("Database ID provided but no TMDB token configured, skipping database search");
-            let mut response = HttpResponse::Ok();
-            if let Some(cookies) = data.cookies_header {
-                response.insert_header(("X-Session-Cookies", cookies));
+        None
+    }
+}
+
+/// Parses a comma separated (and possibly url-encoded) list of category ids, names or slugs.
+fn parse_category_list(list: &str) -> Result<Option<Vec<usize>>, String> {
+    let decoded = urlencoding::decode(list).unwrap_or(std::borrow::Cow::Borrowed(list));
+    let parsed = decoded
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| resolve_category(s, false).and_then(|id| known_category(id, true)))
+        .collect::<Result<Vec<usize>, String>>()?;
+    Ok((!parsed.is_empty()).then_some(parsed))
+}
+
+/// Checks a requested category id: YGG searches the whole site for an id it
+/// doesn't know. With `newznab`, mapped Newznab ids are accepted too.
+fn known_category(id: usize, newznab: bool) -> Result<usize, String> {
+    match is_ygg_category(id) || (newznab && ygg_categories_from_newznab(id).is_some()) {
+        true => Ok(id),
+        false => Err(format!("Unknown category: {}", id)),
+    }
+}
+
+/// Replaces the Newznab ids of a category list by the matching YGG sub-categories.
+/// Ids that are neither YGG nor mapped Newznab categories are dropped.
+pub(crate) fn expand_newznab_categories(ids: Vec<usize>) -> Vec<usize> {
+    let mut expanded: Vec<usize> = Vec::new();
+    for id in ids {
+        let ids = match ygg_categories_from_newznab(id) {
+            Some(ids) => ids,
+            None if is_ygg_category(id) => vec![id],
+            None => {
+                debug!("Ignoring unknown category {}", id);
+                continue;
+            }
+        };
+        for id in ids {
+            if !expanded.contains(&id) {
+                expanded.push(id);
             }
-            return Ok(response.json(Vec::<Value>::new()));
         }
     }
+    expanded
+}
 
-    // Prowlarr RSS feed compatibility trick
-    if name.is_empty() {
-        if connarr.is_some() {
-            order = Some(Order::Descending);
-            sort = Some(Sort::PublishDate);
+/// Titles of the searched media from TMDB, when a TMDB/IMDB/TVDB id is given (`None`
+/// otherwise). The list is empty when the titles could not be fetched.
+pub(crate) async fn database_titles(
+    config: &Config,
+    tmdbid: Option<String>,
+    imdbid: Option<String>,
+    tvdbid: Option<String>,
+    mode: SearchMode,
+) -> Option<(Vec<String>, &'static str)> {
+    let (id, db_type, db_name) = match (tmdbid, imdbid, tvdbid) {
+        (Some(id), _, _) => (id, TMDB, "TMDB"),
+        (None, Some(id), _) => (id, IMDB, "IMDB"),
+        (None, None, Some(id)) => (id, TVDB, "TVDB"),
+        (None, None, None) => return None,
+    };
+
+    let Some(tmdb_token) = &config.tmdb_token else {
+        warn!("Database ID provided but no TMDB token configured, skipping database search");
+        explain::record(|explain| {
+            explain.database = Some(DatabaseLookup {
+                source: db_name,
+                id,
+                error: Some("No TMDB token configured".to_string()),
+                elapsed_ms: 0,
+            })
+        });
+        return Some((vec![], db_name));
+    };
+
+    let media_type = match mode {
+        SearchMode::Tv => MediaType::Tv,
+        _ => MediaType::Movie,
+    };
+
+    let (result, elapsed) = explain::timed(crate::dbs::get_queries(
+        id.clone(),
+        tmdb_token,
+        db_type,
+        media_type,
+    ))
+    .await;
+    let (queries, error) = match result {
+        Ok(queries) => {
+            debug!(
+                "Got {} queries from {} for ID {}",
+                queries.len(),
+                db_name,
+                id
+            );
+            (queries, None)
+        }
+        Err(e) => {
+            warn!("Failed to get {} queries for ID {}: {}", db_name, id, e);
+            (vec![], Some(e.to_string()))
+        }
+    };
+    explain::record(|explain| {
+        explain.database = Some(DatabaseLookup {
+            source: db_name,
+            id,
+            error,
+            elapsed_ms: elapsed.as_millis() as u64,
+        });
+        explain.queries = queries.clone();
+    });
+    Some((queries, db_name))
+}
+
+/// Runs a search the same way `/search` does: TMDB/IMDB/TVDB expansion, bulk category
+/// search or a plain YGG search, renewing the session when it expired.
+pub(crate) async fn search_torrents(
+    data: &MaybeCustomClient,
+    config: &Config,
+    params: SearchParams,
+) -> Result<Vec<Torrent>, Box<dyn std::error::Error>> {
+    let SearchParams {
+        name,
+        fields,
+        offset,
+        limit,
+        category,
+        sub_category,
+        mut sort,
+        mut order,
+        categories,
+        connarr,
+        quote_search,
+        ban_words,
+        filters,
+        tmdbid,
+        imdbid,
+        tvdbid,
+        mode,
+        season,
+        episode,
+    } = params;
+    let name = name.as_str();
+    let fields = &fields;
+
+    if let Some((queries, db_name)) = database_titles(config, tmdbid, imdbid, tvdbid, mode).await {
+        if queries.is_empty() {
+            return Ok(vec![]);
+        }
+        let results = match EpisodeQuery::new(season, episode) {
+            Some(episode_query) => {
+                batch_episode_search(
+                    data,
+                    &data.client,
+                    &queries,
+                    fields,
+                    episode_query,
+                    offset,
+                    limit,
+                    category,
+                    categories,
+                    sub_category,
+                    sort,
+                    order,
+                    ban_words,
+                    quote_search,
+                    &filters,
+                    config,
+                )
+                .await?
+            }
+            None => {
+                batch_best_search(
+                    &data.client,
+                    queries,
+                    fields,
+                    offset,
+                    limit,
+                    category,
+                    sub_category,
+                    sort,
+                    order,
+                    ban_words,
+                    quote_search,
+                    &filters,
+                    config,
+                )
+                .await?
+            }
+        };
+
+        if !results.is_empty() {
+            info!("{} torrents found via {} search", results.len(), db_name);
+        } else {
+            debug!("{} search returned no results", db_name);
         }
+        return Ok(results);
+    }
+
+    // Season/episode variants, filtered down to matching releases
+    if let Some(episode_query) = EpisodeQuery::new(season, episode).filter(|_| !name.is_empty()) {
+        let results = batch_episode_search(
+            data,
+            &data.client,
+            &[name.to_string()],
+            fields,
+            episode_query,
+            offset,
+            limit,
+            category,
+            categories,
+            sub_category,
+            sort,
+            order,
+            ban_words,
+            quote_search,
+            &filters,
+            config,
+        )
+        .await?;
+
+        info!("{} torrents found via episode search", results.len());
+        return Ok(results);
+    }
+
+    // Prowlarr RSS feed compatibility trick
+    if name.is_empty() && fields.is_empty() && connarr {
+        order = Some(Order::Descending);
+        sort = Some(Sort::PublishDate);
     }
 
     // Bulk category search when categories are provided without a specific category
-    if category.is_none() && categories_list.is_some() {
-        let cats = categories_list.unwrap();
+    if let (None, Some(cats)) = (category, categories) {
         debug!(
             "Performing bulk search across {} categories: {:?}",
             cats.len(),
@@ -388,97 +1080,75 @@ pub async fn ygg_search(
         let results = batch_category_search(
             &data.client,
             name,
+            fields,
             offset,
+            limit,
             cats,
             sub_category,
             sort,
             order,
-            ban_words.clone(),
+            ban_words,
             quote_search,
-            &config,
+            &filters,
+            config,
         )
         .await?;
 
         info!("{} torrents found via bulk category search", results.len());
-        let torrent_json: Vec<Value> = results.into_iter().map(|t| t.to_json()).collect();
-        let mut response = HttpResponse::Ok();
-        if let Some(cookies) = data.cookies_header {
-            response.insert_header(("X-Session-Cookies", cookies));
-        }
-        return Ok(response.json(torrent_json));
+        return Ok(results);
     }
 
-    let torrents = search(
+    // Filtered results are fetched from the start, then the window is cut out of
+    // the results left, as in the batches
+    let (query_offset, query_limit) = match filters.is_empty() {
+        true => (offset, limit),
+        false => batch_window(offset, limit),
+    };
+    let (torrents, elapsed) = explain::timed(search_window(
         &data.client,
         name,
-        offset,
+        fields,
+        query_offset,
+        query_limit,
         category,
         sub_category,
         sort,
         order,
         ban_words.clone(),
         quote_search,
-    )
+    ))
     .await;
 
     match torrents {
         Ok(torrents) => {
-            let json: Vec<Value> = torrents.into_iter().map(|t| t.to_json()).collect();
-            info!("{} torrents found", json.len());
-            let mut response = HttpResponse::Ok();
-            if let Some(cookies) = data.cookies_header {
-                response.insert_header(("X-Session-Cookies", cookies));
+            let mut torrents = filters.apply(torrents);
+            explain::record(|explain| {
+                let report = QueryReport::new(name, category, Ok(torrents.len()), elapsed);
+                explain.searches = vec![report];
+                explain.outcome = Some("Single YGG search".to_string());
+            });
+            if matches!(sort, Some(Sort::Relevance)) {
+                sort_results(&mut torrents, &[name.to_string()], sort, order);
+            }
+            if !filters.is_empty() {
+                apply_window(&mut torrents, offset, limit);
             }
-            Ok(response.json(json))
+            info!("{} torrents found", torrents.len());
+            Ok(torrents)
         }
         Err(e) => {
             // If session expired and NOT using custom cookies, try to renew
             if e.to_string().contains("Session expired") && !data.is_custom {
-                info!("Trying to renew session...");
-                let new_client = crate::auth::login(
-                    config.username.as_str(),
-                    config.password.as_str(),
-                    true,
-                    config.flaresolverr_url.as_deref(),
-                )
-                .await?;
-
-                // Transfer cookies only in Direct mode
-                if let (Some(new_wreq), Some(shared_wreq)) = (
-                    new_client.as_wreq_client(),
-                    data.shared_client.as_wreq_client(),
-                ) {
-                    let domain = crate::DOMAIN.lock()?;
-                    let url = wreq::Url::parse(&format!("https://{}/", domain))?;
-                    if let Some(cookies) = new_wreq.get_cookies(&url) {
-                        shared_wreq.clear_cookies();
-                        for cookie_str in cookies.to_str().unwrap_or("").split(';') {
-                            let cookie_str = cookie_str.trim();
-                            if cookie_str.is_empty() {
-                                continue;
-                            }
-                            let parts: Vec<&str> = cookie_str.splitn(2, '=').collect();
-                            if parts.len() != 2 {
-                                continue;
-                            }
-                            let cookie =
-                                wreq::cookie::CookieBuilder::new(parts[0].trim(), parts[1].trim())
-                                    .domain(domain.as_str())
-                                    .path("/")
-                                    .http_only(true)
-                                    .secure(true)
-                                    .build();
-                            shared_wreq.set_cookie(&url, cookie);
-                        }
-                    }
-                    drop(domain);
-                }
+                explain::record(|explain| explain.session_renewed = true);
+                let new_client = renew_session(data, config).await?;
 
                 info!("Session renewed, retrying search...");
-                let torrents = search(
+                let torrents = search_window(
                     &new_client,
                     name,
-                    offset,
+                    fields,
+                    query_offset,
+                    query_limit,
                     category,
                     sub_category,
                     sort,
@@ -487,13 +1157,20 @@ pub async fn ygg_search(
                     quote_search,
                 )
                 .await?;
-                let json: Vec<Value> = torrents.into_iter().map(|t| t.to_json()).collect();
-                info!("{} torrents found", json.len());
-                let mut response = HttpResponse::Ok();
-                if let Some(cookies) = data.cookies_header {
-                    response.insert_header(("X-Session-Cookies", cookies));
+                let mut torrents = filters.apply(torrents);
+                explain::record(|explain| {
+                    let report = QueryReport::new(name, category, Ok(torrents.len()), elapsed);
+                    explain.searches = vec![report];
+                    explain.outcome = Some("Single YGG search".to_string());
+                });
+                if matches!(sort, Some(Sort::Relevance)) {
+                    sort_results(&mut torrents, &[name.to_string()], sort, order);
                 }
-                Ok(response.json(json))
+                if !filters.is_empty() {
+                    apply_window(&mut torrents, offset, limit);
+                }
+                info!("{} torrents found", torrents.len());
+                Ok(torrents)
             } else {
                 error!("Search error: {}", e);
                 Err(e)
@@ -501,3 +1178,80 @@ pub async fn ygg_search(
         }
     }
 }
+
+#[get("/search")]
+pub async fn ygg_search(
+    data: MaybeCustomClient,
+    config: web::Data<Config>,
+    req_data: HttpRequest,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let query = req_data.query_string();
+    debug!("Received query: {}", query);
+    let qs = QString::from(query);
+    load_requested_options(&data, &config, &qs).await;
+    let params = match SearchParams::from_query(&qs) {
+        Ok(params) => params,
+        Err(e) => return Ok(HttpResponse::BadRequest().body(e)),
+    };
+
+    let no_cache = qs.get("no_cache").map(|s| s == "true").unwrap_or(false);
+    if wants_stream(&req_data, &qs) {
+        return Ok(stream_search(data, config, params, no_cache).await);
+    }
+
+    let explain = qs.get("explain").map(|s| s == "true").unwrap_or(false);
+    let search = with_cache_scope(no_cache, search_torrents(&data, &config, params));
+    let ((torrents, cache_status), explain) = with_explain_scope(explain, search).await;
+    let torrents = torrents?;
+
+    let json: Vec<Value> = torrents
+        .into_iter()
+        .map(|t| t.to_json(!data.is_custom))
+        .collect();
+    let mut response = HttpResponse::Ok();
+    response.insert_header(("X-Cache", cache_status.as_str()));
+    if let Some(cookies) = data.cookies_header {
+        response.insert_header(("X-Session-Cookies", cookies));
+    }
+    match explain {
+        Some(explain) => Ok(response.json(json!({ "results": json, "explain": explain }))),
+        None => Ok(response.json(json)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_window() {
+        assert_eq!(batch_window(Some(40), Some(20)), (None, Some(60)));
+        assert_eq!(batch_window(None, Some(20)), (None, Some(20)));
+        assert_eq!(batch_window(Some(50), None), (Some(50), None));
+
+        let mut torrents: Vec<Torrent> = (1..=10)
+            .map(|id| Torrent {
+                id,
+                ..Default::default()
+            })
+            .collect();
+        apply_window(&mut torrents, Some(4), Some(3));
+        assert_eq!(torrents.iter().map(|t| t.id).collect::<Vec<_>>(), [5, 6, 7]);
+        apply_window(&mut torrents, Some(10), Some(3));
+        assert!(torrents.is_empty());
+    }
+    #[test]
+    fn test_unknown_categories() {
+        assert_eq!(
+            expand_newznab_categories(vec![5070, 2183, 5030, 2040, 9999]),
+            vec![2179, 2183]
+        );
+        assert!(expand_newznab_categories(vec![5030, 5040]).is_empty());
+
+        let params = SearchParams::from_query(&QString::from("categories=2183,2000")).unwrap();
+        assert_eq!(params.categories, Some(vec![2183, 2178]));
+        assert!(SearchParams::from_query(&QString::from("categories=2183,5030")).is_err());
+        assert!(SearchParams::from_query(&QString::from("category=2040")).is_err());
+        assert!(SearchParams::from_query(&QString::from("sub_category=2000")).is_err());
+    }
+}
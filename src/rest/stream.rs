@@ -0,0 +1,243 @@
+use crate::cache::with_cache_scope;
+use crate::config::Config;
+use crate::episode::EpisodeQuery;
+use crate::parser::Torrent;
+use crate::relevance::score_torrents;
+use crate::rest::client_extractor::{MaybeCustomClient, renew_session};
+use crate::rest::search::{SearchParams, database_titles};
+use crate::search::{Sort, search_window};
+use actix_web::{HttpRequest, HttpResponse, web};
+use futures::StreamExt;
+use futures::stream::FuturesUnordered;
+use qstring::QString;
+use serde_json::json;
+use std::collections::HashSet;
+
+/// Whether the client asked for a streamed search (`stream=true` or
+/// `Accept: application/x-ndjson`).
+pub(crate) fn wants_stream(req: &HttpRequest, qs: &QString) -> bool {
+    qs.get("stream").is_some_and(|s| s == "true")
+        || req
+            .headers()
+            .get("Accept")
+            .and_then(|accept| accept.to_str().ok())
+            .is_some_and(|accept| accept.contains("application/x-ndjson"))
+}
+
+/// YGG searches of a request as `(query, category)` pairs, with the titles the
+/// results are scored against for `Sort::Relevance`.
+async fn search_plan(
+    config: &Config,
+    params: &SearchParams,
+) -> (Vec<(String, Option<usize>)>, Vec<String>) {
+    let titles = match database_titles(
+        config,
+        params.tmdbid.clone(),
+        params.imdbid.clone(),
+        params.tvdbid.clone(),
+        params.mode,
+    )
+    .await
+    {
+        Some((titles, _)) => titles,
+        None => vec![params.name.clone()],
+    };
+
+    let queries: Vec<String> = match EpisodeQuery::new(params.season, params.episode) {
+        Some(episode_query) if !titles.iter().all(|t| t.is_empty()) => titles
+            .iter()
+            .flat_map(|title| episode_query.queries(title))
+            .collect(),
+        _ => titles.clone(),
+    };
+    let cats_list: Vec<Option<usize>> = match (params.category, &params.categories) {
+        (None, Some(cats)) => cats.iter().map(|cat| Some(*cat)).collect(),
+        _ => vec![params.category],
+    };
+    let searches = queries
+        .iter()
+        .flat_map(|query| cats_list.iter().map(move |cat| (query.clone(), *cat)))
+        .collect();
+    (searches, titles)
+}
+
+/// Streams the results of a search as NDJSON: torrents are written as soon as
+/// the YGG search they come from finishes, then a summary line reports the count
+/// and the outcome of each query.
+pub(crate) async fn stream_search(
+    data: MaybeCustomClient,
+    config: web::Data<Config>,
+    params: SearchParams,
+    no_cache: bool,
+) -> HttpResponse {
+    let (searches, titles) = search_plan(&config, &params).await;
+    let episode_query = EpisodeQuery::new(params.season, params.episode);
+    let client = data.client.clone();
+    let is_custom = data.is_custom;
+    let cookies_header = data.cookies_header.clone();
+
+    let stream = async_stream::stream! {
+        let mut pending: FuturesUnordered<_> = searches
+            .iter()
+            .enumerate()
+            .map(|(idx, (query, category))| {
+                let search = search_window(
+                    &client,
+                    query,
+                    &params.fields,
+                    params.offset,
+                    params.limit,
+                    *category,
+                    params.sub_category,
+                    params.sort,
+                    params.order,
+                    params.ban_words.clone(),
+                    params.quote_search,
+                );
+                async move { (idx, with_cache_scope(no_cache, search).await) }
+            })
+            .collect();
+
+        let mut seen: HashSet<usize> = HashSet::new();
+        let mut summaries = vec![serde_json::Value::Null; searches.len()];
+        let mut renewed = None;
+        let mut total = 0;
+
+        while let Some((idx, (result, cache_status))) = pending.next().await {
+            let (query, category) = &searches[idx];
+
+            // A single renewal for the whole stream, then the query is retried
+            let (result, cache_status) = match result {
+                Err(e) if e.to_string().contains("Session expired") && !is_custom => {
+                    if renewed.is_none() {
+                        info!("Session expired during streamed search, renewing...");
+                        renewed = renew_session(&data, &config)
+                            .await
+                            .map_err(|e| warn!("Session renewal failed: {}", e))
+                        .ok();
+                    }
+                    match &renewed {
+                        Some(new_client) => {
+                            let search = search_window(
+                                new_client,
+                                query,
+                                &params.fields,
+                                params.offset,
+                                params.limit,
+                                *category,
+                                params.sub_category,
+                                params.sort,
+                                params.order,
+                                params.ban_words.clone(),
+                                params.quote_search,
+                            );
+                            with_cache_scope(no_cache, search).await
+                        }
+                        None => (Err(e), cache_status),
+                    }
+                }
+                result => (result, cache_status),
+            };
+
+            let torrents = match result {
+                Ok(torrents) => torrents,
+                Err(e) => {
+                    warn!("Streamed search failed for query {}: {}", query, e);
+                    summaries[idx] = json!({
+                        "query": query,
+                        "category": category,
+                        "error": e.to_string(),
+                    });
+                    continue;
+                }
+            };
+
+            let mut torrents: Vec<Torrent> = params
+                .filters
+                .apply(torrents)
+                .into_iter()
+                .filter(|t| episode_query.is_none_or(|q| q.matches(&t.name)))
+                .filter(|t| seen.insert(t.id))
+                .collect();
+            if matches!(params.sort, Some(Sort::Relevance)) {
+                score_torrents(&mut torrents, &titles);
+            }
+            Torrent::sort(&mut torrents, params.sort, params.order);
+            if let Some(limit) = params.limit {
+                torrents.truncate(limit.saturating_sub(total));
+            }
+
+            summaries[idx] = json!({
+                "query": query,
+                "category": category,
+                "count": torrents.len(),
+                "cache": cache_status.as_str(),
+            });
+            total += torrents.len();
+            for torrent in torrents {
+                let line = format!("{}\n", torrent.to_json(!is_custom));
+                yield Ok::<_, actix_web::Error>(web::Bytes::from(line));
+            }
+
+            // The queries still running are dropped, sparing the YGG rate limit
+            if params.limit.is_some_and(|limit| total >= limit) {
+                debug!("Stream limit reached, cancelling {} queries", pending.len());
+                break;
+            }
+        }
+        drop(pending);
+
+        for (idx, (query, category)) in searches.iter().enumerate() {
+            if summaries[idx].is_null() {
+                summaries[idx] = json!({ "query": query, "category": category, "skipped": true });
+            }
+        }
+
+        let summary = json!({ "summary": { "count": total, "queries": summaries } });
+        yield Ok(web::Bytes::from(format!("{}\n", summary)));
+    };
+
+    let mut response = HttpResponse::Ok();
+    response.content_type("application/x-ndjson");
+    if let Some(cookies) = cookies_header {
+        response.insert_header(("X-Session-Cookies", cookies));
+    }
+    response.streaming(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_wants_stream() {
+        let req = TestRequest::default().to_http_request();
+        assert!(wants_stream(&req, &QString::from("stream=true")));
+        assert!(!wants_stream(&req, &QString::from("q=test")));
+
+        let req = TestRequest::default()
+            .insert_header(("Accept", "application/x-ndjson"))
+            .to_http_request();
+        assert!(wants_stream(&req, &QString::from("q=test")));
+    }
+
+    #[tokio::test]
+    async fn test_search_plan() {
+        let params = SearchParams {
+            name: "Breaking Bad".to_string(),
+            categories: Some(vec![2179, 2184]),
+            season: Some(2),
+            episode: Some(5),
+            ..Default::default()
+        };
+        let (searches, titles) = search_plan(&Config::default(), &params).await;
+        assert_eq!(titles, vec!["Breaking Bad"]);
+        let queries = EpisodeQuery::new(Some(2), Some(5))
+            .unwrap()
+            .queries("Breaking Bad");
+        assert_eq!(searches.len(), queries.len() * 2);
+        assert_eq!(searches[0], (queries[0].clone(), Some(2179)));
+        assert_eq!(searches[1], (queries[0].clone(), Some(2184)));
+    }
+}
@@ -0,0 +1,76 @@
+use crate::cache::with_cache_scope;
+use crate::categories::{CATEGORIES_CACHE, resolve_category};
+use crate::config::Config;
+use crate::parser::Torrent;
+use crate::rest::client_extractor::{MaybeCustomClient, renew_session};
+use crate::search::{Order, Sort};
+use crate::top::{TopPeriod, filter_category, get_top};
+use actix_web::{HttpRequest, HttpResponse, get, web};
+use qstring::QString;
+use serde_json::Value;
+
+#[get("/top/{period:day|week|month}")]
+pub async fn top_torrents(
+    data: MaybeCustomClient,
+    config: web::Data<Config>,
+    req_data: HttpRequest,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let period = req_data.match_info().get("period").unwrap();
+    let period = period.parse::<TopPeriod>()?;
+    let qs = QString::from(req_data.query_string());
+
+    let category = match qs.get("category").filter(|s| !s.is_empty()) {
+        Some(category) => match resolve_category(category, false) {
+            Ok(category) => Some(category),
+            Err(e) => return Ok(HttpResponse::BadRequest().body(e)),
+        },
+        None => None,
+    };
+    let sort = match qs.get("sort").map(|s| s.parse::<Sort>()) {
+        Some(Ok(Sort::Relevance)) => {
+            return Ok(HttpResponse::BadRequest().body("relevance needs a search query"));
+        }
+        Some(Ok(sort)) => Some(sort),
+        Some(Err(e)) => return Ok(HttpResponse::BadRequest().body(e)),
+        None => None,
+    };
+    let order = match qs.get("order").map(|s| s.parse::<Order>()) {
+        Some(Ok(order)) => Some(order),
+        Some(Err(e)) => return Ok(HttpResponse::BadRequest().body(e)),
+        None => None,
+    };
+    let no_cache = qs.get("no_cache").map(|s| s == "true").unwrap_or(false);
+
+    let (torrents, cache_status) = with_cache_scope(no_cache, async {
+        match get_top(&data.client, period).await {
+            Err(e) if e.to_string().contains("Session expired") && !data.is_custom => {
+                let new_client = renew_session(&data, &config).await?;
+
+                info!("Session renewed, retrying to get top torrents...");
+                get_top(&new_client, period).await
+            }
+            torrents => torrents,
+        }
+    })
+    .await;
+    let mut torrents = torrents?;
+
+    if let Some(category) = category {
+        let categories = CATEGORIES_CACHE.get().map(Vec::as_slice).unwrap_or(&[]);
+        torrents = filter_category(torrents, category, categories);
+    }
+    if sort.is_some() || order.is_some() {
+        Torrent::sort(&mut torrents, sort, order);
+    }
+
+    let json: Vec<Value> = torrents
+        .into_iter()
+        .map(|t| t.to_json(!data.is_custom))
+        .collect();
+    let mut response = HttpResponse::Ok();
+    response.insert_header(("X-Cache", cache_status.as_str()));
+    if let Some(cookies) = data.cookies_header {
+        response.insert_header(("X-Session-Cookies", cookies));
+    }
+    Ok(response.json(json))
+}
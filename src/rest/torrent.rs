@@ -1,9 +1,9 @@
-use crate::DOMAIN;
+use crate::bencode::TorrentMeta;
+use crate::categories::resolve_category;
 use crate::config::Config;
-use crate::rest::client_extractor::MaybeCustomClient;
-use actix_web::{HttpRequest, HttpResponse, get, web};
-use serde_json::Value;
-use tokio::time::{Duration, sleep};
+use crate::rest::client_extractor::{MaybeCustomClient, renew_session};
+use actix_web::{HttpRequest, HttpResponse, get, post, web};
+use qstring::QString;
 
 #[get("/torrent/{id:[0-9]+}")]
 pub async fn download_torrent(
@@ -14,85 +14,253 @@ pub async fn download_torrent(
     let id = req_data.match_info().get("id").unwrap();
     let id = id.parse::<usize>()?;
 
-    let domain_lock = DOMAIN.lock()?;
-    let cloned_guard = domain_lock.clone();
-    let domain = cloned_guard.as_str();
-    drop(domain_lock);
+    let bytes = crate::torrent::download_torrent_file(
+        &data.client,
+        id,
+        config.turbo_enabled.unwrap_or(false),
+        !data.is_custom,
+    )
+    .await?;
 
-    // Request token
-    let url = format!("https://{}/engine/start_download_timer", domain);
-    let body = format!("torrent_id={}", id);
+    let mut response_builder = HttpResponse::Ok();
+    response_builder
+        .content_type("application/x-bittorrent")
+        .append_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"{}.torrent\"", id),
+        ));
+
+    if let Some(cookies) = data.cookies_header {
+        response_builder.insert_header(("X-Session-Cookies", cookies));
+    }
+
+    Ok(response_builder.body(bytes))
+}
+
+#[post("/torrent/{id:[0-9]+}/send")]
+pub async fn send_torrent(
+    data: MaybeCustomClient,
+    config: web::Data<Config>,
+    req_data: HttpRequest,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let id = req_data.match_info().get("id").unwrap();
+    let id = id.parse::<usize>()?;
+    let qs = QString::from(req_data.query_string());
 
-    debug!("Request download token {} {}", url, body);
+    // The only profile is used when no client is given
+    let profiles = config.download_clients.as_deref().unwrap_or(&[]);
+    let profile = match qs.get("client") {
+        Some(name) => profiles.iter().find(|profile| profile.name == name),
+        None if profiles.len() == 1 => profiles.first(),
+        None => None,
+    };
+    let Some(profile) = profile else {
+        let names: Vec<&str> = profiles.iter().map(|p| p.name.as_str()).collect();
+        return Ok(HttpResponse::BadRequest().body(format!(
+            "Unknown download client. Valid choices: {}",
+            names.join(", ")
+        )));
+    };
+    // The category the torrent was found in, unless overridden
+    let category = match qs.get("category").filter(|s| !s.is_empty()) {
+        Some(category) => match resolve_category(category, false) {
+            Ok(category) => Some(category),
+            Err(e) => return Ok(HttpResponse::BadRequest().body(e)),
+        },
+        None => crate::torrent::known_category(id),
+    };
 
-    let response = data.client.post_form(&url, &body).await?;
+    let bytes = crate::torrent::download_torrent_file(
+        &data.client,
+        id,
+        config.turbo_enabled.unwrap_or(false),
+        !data.is_custom,
+    )
+    .await?;
+    let meta = TorrentMeta::from_bytes(&bytes)?;
+    crate::torrent::remember_infohash(id, &meta.infohash, &meta.name);
 
-    if !(200..300).contains(&response.status) {
-        return Err(format!("Failed to get token: {}", response.status).into());
+    let label = profile.label(category);
+    let filename = format!("{}.torrent", id);
+    if let Err(e) = profile.send(&filename, &bytes, label.as_deref()).await {
+        error!("Failed to send torrent {} to {}: {}", id, profile.name, e);
+        return Ok(HttpResponse::BadGateway().body(e.to_string()));
     }
+    info!("Torrent {} sent to {}", id, profile.name);
 
-    let json: Value = serde_json::from_str(&response.body)?;
-    debug!("Response {}", json);
+    let mut response = HttpResponse::Ok();
+    if let Some(cookies) = data.cookies_header {
+        response.insert_header(("X-Session-Cookies", cookies));
+    }
+    Ok(response.json(serde_json::json!({
+        "id": id,
+        "name": meta.name,
+        "infohash": meta.infohash,
+        "client": profile.name,
+        "label": label,
+    })))
+}
+
+#[get("/torrent/info/{path:.+}")]
+pub async fn torrent_info(
+    data: MaybeCustomClient,
+    config: web::Data<Config>,
+    req_data: HttpRequest,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let path = req_data.match_info().get("path").unwrap();
+
+    let details = crate::torrent::get_torrent_details(&data.client, path).await;
+    let details = match details {
+        Err(e) if e.to_string().contains("Session expired") && !data.is_custom => {
+            let new_client = renew_session(&data, &config).await?;
+
+            info!("Session renewed, retrying to get torrent details...");
+            crate::torrent::get_torrent_details(&new_client, path).await
+        }
+        details => details,
+    };
+
+    let details = match details {
+        Ok(details) => details,
+        Err(e) if e.to_string().contains("Torrent not found") => {
+            return Ok(HttpResponse::NotFound().body(e.to_string()));
+        }
+        Err(e) => return Err(e),
+    };
+
+    let mut response = HttpResponse::Ok();
+    if let Some(cookies) = data.cookies_header {
+        response.insert_header(("X-Session-Cookies", cookies));
+    }
+    Ok(response.json(details))
+}
+
+#[get("/torrent/{id:[0-9]+}/meta")]
+pub async fn torrent_meta(
+    data: MaybeCustomClient,
+    config: web::Data<Config>,
+    req_data: HttpRequest,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let id = req_data.match_info().get("id").unwrap();
+    let id = id.parse::<usize>()?;
 
-    let token = json
-        .get("token")
-        .and_then(|h| h.as_str())
-        .ok_or("Token not found in start_download_timer response")?;
+    let bytes = crate::torrent::download_torrent_file(
+        &data.client,
+        id,
+        config.turbo_enabled.unwrap_or(false),
+        !data.is_custom,
+    )
+    .await?;
+    let meta = TorrentMeta::from_bytes(&bytes)?;
+    crate::torrent::remember_infohash(id, &meta.infohash, &meta.name);
 
-    if !config.turbo_enabled.unwrap_or(false) {
-        debug!("Wait 30 secs...");
-        sleep(Duration::from_secs(30)).await;
-        debug!("Wait is over");
+    let mut response = HttpResponse::Ok();
+    if let Some(cookies) = data.cookies_header {
+        response.insert_header(("X-Session-Cookies", cookies));
     }
+    Ok(response.json(meta))
+}
 
-    // Request signed torrent file
-    let url = format!(
-        "https://{}/engine/download_torrent?id={}&token={}",
-        domain, id, token
-    );
-    debug!("download URL {}", url);
-
-    let (status, bytes) = data.client.get_bytes(&url).await?;
-
-    if !(200..300).contains(&status) {
-        if status == 302 {
-            return match crate::utils::get_remaining_downloads(&data.client).await {
-                Ok(0) => {
-                    error!("No remaining downloads");
-                    Err("No remaining downloads".into())
-                }
-                Ok(n) => {
-                    warn!(
-                        "Failed to download torrent, but you have {} remaining downloads, might be caused by an insufficient ratio.",
-                        n
-                    );
-                    Err("Failed to download torrent, but you have remaining downloads.".into())
-                }
-                Err(e) => {
-                    error!("Error while checking remaining downloads: {}", e);
-                    Err("Failed to download torrent and check remaining downloads.".into())
-                }
+#[get("/torrent/{id:[0-9]+}/magnet")]
+pub async fn torrent_magnet(
+    data: MaybeCustomClient,
+    config: web::Data<Config>,
+    req_data: HttpRequest,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let id = req_data.match_info().get("id").unwrap();
+    let id = id.parse::<usize>()?;
+
+    // The magnet of a known infohash announces with the shared account's passkey,
+    // so custom sessions get the announce url of their own .torrent file
+    let known = crate::torrent::known_infohash(id).filter(|_| !data.is_custom);
+    let (infohash, name, magnet) = match known {
+        Some((infohash, name)) => {
+            debug!("Infohash of torrent {} already known", id);
+            let magnet = crate::torrent::magnet_uri(&infohash, &name);
+            (infohash, name, magnet)
+        }
+        None => {
+            debug!(
+                "Infohash of torrent {} unknown, downloading .torrent file",
+                id
+            );
+            let bytes = crate::torrent::download_torrent_file(
+                &data.client,
+                id,
+                config.turbo_enabled.unwrap_or(false),
+                !data.is_custom,
+            )
+            .await?;
+            let meta = TorrentMeta::from_bytes(&bytes)?;
+            crate::torrent::remember_infohash(id, &meta.infohash, &meta.name);
+
+            // The .torrent file already carries the passkeyed announce url
+            let magnet = match &meta.announce {
+                Some(announce) => Some(crate::torrent::build_magnet_uri(
+                    &meta.infohash,
+                    &meta.name,
+                    announce,
+                )),
+                None if data.is_custom => None,
+                None => crate::torrent::magnet_uri(&meta.infohash, &meta.name),
             };
+            (meta.infohash, meta.name, magnet)
         }
-        return Err(format!(
-            "Failed to get torrent file: {} {}",
-            status,
-            String::from_utf8_lossy(&bytes)
-        )
-        .into());
-    }
+    };
 
-    let mut response_builder = HttpResponse::Ok();
-    response_builder
-        .content_type("application/x-bittorrent")
-        .append_header((
-            "Content-Disposition",
-            format!("attachment; filename=\"{}.torrent\"", id),
-        ));
+    let magnet = magnet.ok_or("Passkey unknown, unable to build magnet link")?;
 
+    let mut response = HttpResponse::Ok();
     if let Some(cookies) = data.cookies_header {
-        response_builder.insert_header(("X-Session-Cookies", cookies));
+        response.insert_header(("X-Session-Cookies", cookies));
     }
+    Ok(response.json(serde_json::json!({
+        "id": id,
+        "name": name,
+        "infohash": infohash,
+        "magnet": magnet,
+    })))
+}
 
-    Ok(response_builder.body(bytes))
+#[get("/torrent/{id:[0-9]+}/nfo")]
+pub async fn torrent_nfo(
+    data: MaybeCustomClient,
+    config: web::Data<Config>,
+    req_data: HttpRequest,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let id = req_data.match_info().get("id").unwrap();
+    let id = id.parse::<usize>()?;
+    let qs = QString::from(req_data.query_string());
+    let json = qs.get("format") == Some("json");
+
+    let nfo = crate::nfo::get_nfo(&data.client, id).await;
+    let nfo = match nfo {
+        Err(e) if e.to_string().contains("Session expired") && !data.is_custom => {
+            let new_client = renew_session(&data, &config).await?;
+
+            info!("Session renewed, retrying to get NFO...");
+            crate::nfo::get_nfo(&new_client, id).await
+        }
+        nfo => nfo,
+    };
+
+    let nfo = match nfo {
+        Ok(nfo) => nfo,
+        Err(e) if e.to_string().contains("NFO not found") => {
+            return Ok(HttpResponse::NotFound().body(e.to_string()));
+        }
+        Err(e) => return Err(e),
+    };
+
+    let mut response = HttpResponse::Ok();
+    if let Some(cookies) = data.cookies_header {
+        response.insert_header(("X-Session-Cookies", cookies));
+    }
+    match json {
+        true => Ok(response.json(serde_json::json!({
+            "id": id,
+            "nfo": nfo,
+        }))),
+        false => Ok(response.content_type("text/plain; charset=utf-8").body(nfo)),
+    }
 }
@@ -0,0 +1,125 @@
+use crate::cache::with_cache_scope;
+use crate::categories::CATEGORIES_CACHE;
+use crate::config::Config;
+use crate::rest::client_extractor::MaybeCustomClient;
+use crate::rest::search::{
+    SearchParams, expand_newznab_categories, parse_id_list, search_torrents,
+};
+use crate::search::{MAX_LIMIT, PAGE_SIZE, SearchMode};
+use crate::torznab::{caps_xml, error_xml, from_torznab_category, results_xml};
+use actix_web::{HttpRequest, HttpResponse, get, web};
+use qstring::QString;
+
+#[get("/api")]
+pub async fn torznab_api(
+    data: MaybeCustomClient,
+    config: web::Data<Config>,
+    req_data: HttpRequest,
+) -> HttpResponse {
+    let query = req_data.query_string();
+    debug!("Received Torznab query: {}", query);
+    let qs = QString::from(query);
+
+    let categories = CATEGORIES_CACHE.get().map(Vec::as_slice).unwrap_or(&[]);
+
+    let function = match qs.get("t") {
+        Some(t) => t,
+        None => return xml_error(200, "Missing parameter (t)"),
+    };
+
+    if function == "caps" {
+        return HttpResponse::Ok()
+            .content_type("application/xml; charset=utf-8")
+            .body(caps_xml(categories, config.tmdb_token.is_some()));
+    }
+
+    let mode = match function.parse::<SearchMode>() {
+        Ok(mode) => mode,
+        Err(_) => return xml_error(202, &format!("No such function ({})", function)),
+    };
+
+    let limit = qs
+        .get("limit")
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(PAGE_SIZE)
+        .min(MAX_LIMIT);
+
+    let mut params = SearchParams {
+        name: qs.get("q").unwrap_or("").to_string(),
+        offset: qs.get("offset").and_then(|s| s.parse::<usize>().ok()),
+        limit: Some(limit),
+        categories: qs.get("cat").and_then(parse_id_list).map(|cats| {
+            expand_newznab_categories(cats.into_iter().map(from_torznab_category).collect())
+        }),
+        connarr: true,
+        mode,
+        ..Default::default()
+    };
+
+    if mode == SearchMode::Tv {
+        params.season = qs.get("season").and_then(|s| s.parse::<u32>().ok());
+        params.episode = qs.get("ep").and_then(|s| s.parse::<u32>().ok());
+        params.tvdbid = qs
+            .get("tvdbid")
+            .filter(|id| !id.is_empty())
+            .map(|id| id.to_string());
+    }
+
+    if mode == SearchMode::Movie || mode == SearchMode::Tv {
+        params.tmdbid = qs
+            .get("tmdbid")
+            .filter(|id| !id.is_empty())
+            .map(|id| id.to_string());
+        params.imdbid =
+            qs.get("imdbid")
+                .filter(|id| !id.is_empty())
+                .map(|id| match id.starts_with("tt") {
+                    true => id.to_string(),
+                    false => format!("tt{}", id),
+                });
+    }
+
+    // Only unknown categories requested (`cat=5030`): nothing YGG can search
+    if params.categories.as_ref().is_some_and(Vec::is_empty) {
+        debug!("No known category in Torznab query, returning no results");
+        return HttpResponse::Ok()
+            .content_type("application/rss+xml; charset=utf-8")
+            .body(results_xml(&[], categories, &base_url(&req_data), false));
+    }
+
+    let no_cache = qs.get("no_cache").map(|s| s == "true").unwrap_or(false);
+    let (torrents, cache_status) =
+        with_cache_scope(no_cache, search_torrents(&data, &config, params)).await;
+    let mut torrents = match torrents {
+        Ok(torrents) => torrents,
+        Err(e) => {
+            error!("Torznab search error: {}", e);
+            return xml_error(900, &e.to_string());
+        }
+    };
+    torrents.truncate(limit);
+
+    let mut response = HttpResponse::Ok();
+    response.content_type("application/rss+xml; charset=utf-8");
+    response.insert_header(("X-Cache", cache_status.as_str()));
+    if let Some(cookies) = data.cookies_header {
+        response.insert_header(("X-Session-Cookies", cookies));
+    }
+    response.body(results_xml(
+        &torrents,
+        categories,
+        &base_url(&req_data),
+        !data.is_custom,
+    ))
+}
+
+fn base_url(req_data: &HttpRequest) -> String {
+    let connection_info = req_data.connection_info();
+    format!("{}://{}", connection_info.scheme(), connection_info.host())
+}
+
+fn xml_error(code: u16, description: &str) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("application/xml; charset=utf-8")
+        .body(error_xml(code, description))
+}
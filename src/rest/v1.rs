@@ -0,0 +1,473 @@
+//! Versioned JSON API. Responses are typed structs documented by the OpenAPI
+//! document served at `/api/v1/openapi.json`, so their schema only changes with
+//! the API version.
+
+use crate::cache::with_cache_scope;
+use crate::categories::{CATEGORIES_CACHE, Category, newznab_categories};
+use crate::config::Config;
+use crate::parser::Torrent;
+use crate::release::ReleaseInfo;
+use crate::rest::client_extractor::{MaybeCustomClient, renew_session};
+use crate::rest::infos::get_status;
+use crate::rest::search::{
+    SEARCH_QUERY_PARAMS, SearchParams, load_requested_options, search_torrents,
+};
+use crate::user::UserAccount;
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponse, get, web};
+use qstring::QString;
+use serde::Serialize;
+use utoipa::openapi::path::{Parameter, ParameterBuilder, ParameterIn};
+use utoipa::openapi::{ObjectBuilder, Required};
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Ygégé API", description = "Indexeur YGG Torrent"),
+    paths(v1_search, v1_categories, v1_user, v1_status, v1_openapi),
+    components(schemas(
+        ApiError,
+        TorrentItem,
+        ReleaseInfo,
+        SearchResponse,
+        CategoryItem,
+        SubCategoryItem,
+        UserResponse,
+        StatusResponse
+    ))
+)]
+pub struct ApiDoc;
+
+pub fn config_v1_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api/v1")
+            .service(v1_search)
+            .service(v1_categories)
+            .service(v1_user)
+            .service(v1_status)
+            .service(v1_openapi),
+    );
+}
+
+/// Error returned by every `/api/v1` endpoint.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiError {
+    /// Human readable description
+    pub error: String,
+    /// `INVALID_PARAMETERS`, `YGG_ERROR` or `AUTH_FAILED`
+    pub code: String,
+}
+
+impl ApiError {
+    pub fn response(status: StatusCode, code: &str, error: impl ToString) -> HttpResponse {
+        HttpResponse::build(status).json(ApiError {
+            error: error.to_string(),
+            code: code.to_string(),
+        })
+    }
+
+    /// Maps an error of the YGG scraping layer to its API error.
+    pub fn from_ygg_error(e: &dyn std::error::Error) -> HttpResponse {
+        let message = e.to_string();
+        if message.contains("Session expired") {
+            ApiError::response(StatusCode::UNAUTHORIZED, "AUTH_FAILED", message)
+        } else {
+            ApiError::response(StatusCode::BAD_GATEWAY, "YGG_ERROR", message)
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TorrentItem {
+    pub id: usize,
+    pub name: String,
+    pub category_id: usize,
+    /// Standard Newznab categories of the YGG category
+    pub newznab_categories: Vec<usize>,
+    /// Size in bytes
+    pub size: u64,
+    pub completed: usize,
+    pub seed: usize,
+    pub leech: usize,
+    pub comments_count: usize,
+    /// Upload date, as a unix timestamp
+    pub age_stamp: usize,
+    /// Path of the detail endpoint (`/torrent/info/...`)
+    pub info_url: String,
+    /// YGG detail page
+    pub link: String,
+    /// Download through Ygégé (`/torrent/{id}`)
+    pub download: String,
+    /// Direct YGG download url
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub infohash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub magnet: Option<String>,
+    #[serde(flatten)]
+    pub release: ReleaseInfo,
+    /// Match score against the searched titles, with `sort=relevance`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relevance: Option<i32>,
+}
+
+impl TorrentItem {
+    /// The magnet link announces with the passkey of the shared account, so
+    /// `with_magnet` is false for requests with custom cookies.
+    fn new(torrent: Torrent, with_magnet: bool) -> Self {
+        TorrentItem {
+            download: torrent.get_download_url().unwrap_or_default(),
+            url: torrent.get_url().unwrap_or_default(),
+            magnet: torrent.get_magnet().filter(|_| with_magnet),
+            id: torrent.id,
+            name: torrent.name,
+            newznab_categories: newznab_categories(torrent.category_id),
+            category_id: torrent.category_id,
+            size: torrent.size,
+            completed: torrent.completed,
+            seed: torrent.seed,
+            leech: torrent.leech,
+            comments_count: torrent.comments_count,
+            age_stamp: torrent.age_stamp,
+            info_url: torrent.info_url,
+            link: torrent.link,
+            infohash: torrent.infohash,
+            release: torrent.release,
+            relevance: torrent.relevance,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SearchResponse {
+    /// Number of results in this response
+    pub count: usize,
+    pub offset: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    pub results: Vec<TorrentItem>,
+}
+
+/// Query parameters of `/api/v1/search`, the same as the ones of `/search`.
+pub struct SearchQuery;
+
+impl IntoParams for SearchQuery {
+    fn into_params(parameter_in_provider: impl Fn() -> Option<ParameterIn>) -> Vec<Parameter> {
+        SEARCH_QUERY_PARAMS
+            .iter()
+            .map(|(name, schema_type, description)| {
+                ParameterBuilder::new()
+                    .name(*name)
+                    .parameter_in(parameter_in_provider().unwrap_or(ParameterIn::Query))
+                    .required(Required::False)
+                    .description(Some(*description))
+                    .schema(Some(ObjectBuilder::new().schema_type(schema_type.clone())))
+                    .build()
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CategoryItem {
+    pub id: usize,
+    pub name: String,
+    pub sub_categories: Vec<SubCategoryItem>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SubCategoryItem {
+    pub id: usize,
+    pub name: String,
+}
+
+impl From<&Category> for CategoryItem {
+    fn from(category: &Category) -> Self {
+        CategoryItem {
+            id: category.id,
+            name: category.name.clone(),
+            sub_categories: category
+                .sub_categories
+                .iter()
+                .map(|sub_category| SubCategoryItem {
+                    id: sub_category.id,
+                    name: sub_category.name.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// YGG account of the configured user. The passkey is not exposed.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UserResponse {
+    pub username: String,
+    pub rank: String,
+    pub join_date: String,
+    pub last_activity: String,
+    pub torrents_count: u16,
+    pub comments_count: u16,
+    pub reputation_score: i32,
+    /// Uploaded bytes
+    pub uploaded: u128,
+    /// Downloaded bytes
+    pub downloaded: u128,
+    pub ratio: f32,
+    pub avatar_url: String,
+    pub email: String,
+    pub age: Option<u16>,
+    pub gender: Option<String>,
+    pub country: Option<String>,
+    pub country_code: Option<String>,
+}
+
+impl From<UserAccount> for UserResponse {
+    fn from(account: UserAccount) -> Self {
+        UserResponse {
+            username: account.username,
+            rank: account.rank,
+            join_date: account.join_date,
+            last_activity: account.last_activity,
+            torrents_count: account.torrents_count,
+            comments_count: account.comments_count,
+            reputation_score: account.reputation_score,
+            uploaded: account.uploaded,
+            downloaded: account.downloaded,
+            ratio: account.ratio,
+            avatar_url: account.avatar_url,
+            email: account.email,
+            age: account.age,
+            gender: account.gender,
+            country: account.country,
+            country_code: account.country_code,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StatusResponse {
+    pub domain: String,
+    /// `authenticated`, `not_authenticated` or `auth_error`
+    pub auth: String,
+    /// `ok` or `failed`
+    pub search: String,
+    /// `ok` or `failed`
+    pub user_info: String,
+    /// `reachable`, `unreachable` or `timeout`
+    pub domain_reachability: String,
+    /// `resolves` or `does_not_resolve`
+    pub domain_dns: String,
+    /// `ok`, `failed` or `n/a`
+    pub parsing: String,
+    /// `enabled` or `disabled`
+    pub tmdb_integration: String,
+    /// `-1` when unknown
+    pub remaining_downloads: i32,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/search",
+    tag = "search",
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "Search results", body = SearchResponse),
+        (status = 400, description = "Invalid parameters", body = ApiError),
+        (status = 401, description = "YGG session expired", body = ApiError),
+        (status = 502, description = "YGG error", body = ApiError)
+    )
+)]
+#[get("/search")]
+pub async fn v1_search(
+    data: MaybeCustomClient,
+    config: web::Data<Config>,
+    req_data: HttpRequest,
+) -> HttpResponse {
+    let qs = QString::from(req_data.query_string());
+    load_requested_options(&data, &config, &qs).await;
+    let params = match SearchParams::from_query(&qs) {
+        Ok(params) => params,
+        Err(e) => return ApiError::response(StatusCode::BAD_REQUEST, "INVALID_PARAMETERS", e),
+    };
+    let offset = params.offset.unwrap_or(0);
+    let limit = params.limit;
+
+    let no_cache = qs.get("no_cache").map(|s| s == "true").unwrap_or(false);
+    let (torrents, cache_status) =
+        with_cache_scope(no_cache, search_torrents(&data, &config, params)).await;
+    let torrents = match torrents {
+        Ok(torrents) => torrents,
+        Err(e) => return ApiError::from_ygg_error(e.as_ref()),
+    };
+
+    let results: Vec<TorrentItem> = torrents
+        .into_iter()
+        .map(|torrent| TorrentItem::new(torrent, !data.is_custom))
+        .collect();
+    let mut response = HttpResponse::Ok();
+    response.insert_header(("X-Cache", cache_status.as_str()));
+    if let Some(cookies) = data.cookies_header {
+        response.insert_header(("X-Session-Cookies", cookies));
+    }
+    response.json(SearchResponse {
+        count: results.len(),
+        offset,
+        limit,
+        results,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/categories",
+    tag = "categories",
+    responses(
+        (status = 200, description = "Categories and sub-categories", body = Vec<CategoryItem>),
+        (status = 502, description = "YGG error", body = ApiError)
+    )
+)]
+#[get("/categories")]
+pub async fn v1_categories(data: MaybeCustomClient) -> HttpResponse {
+    let categories = match CATEGORIES_CACHE.get() {
+        Some(categories) => categories.clone(),
+        None => match crate::categories::scrape_categories(&data.client).await {
+            Ok(categories) => {
+                let _ = CATEGORIES_CACHE.set(categories.clone());
+                categories
+            }
+            Err(e) => return ApiError::from_ygg_error(e.as_ref()),
+        },
+    };
+
+    let categories: Vec<CategoryItem> = categories.iter().map(CategoryItem::from).collect();
+    let mut response = HttpResponse::Ok();
+    if let Some(cookies) = data.cookies_header {
+        response.insert_header(("X-Session-Cookies", cookies));
+    }
+    response.json(categories)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/user",
+    tag = "user",
+    responses(
+        (status = 200, description = "YGG account", body = UserResponse),
+        (status = 401, description = "YGG session expired", body = ApiError),
+        (status = 502, description = "YGG error", body = ApiError)
+    )
+)]
+#[get("/user")]
+pub async fn v1_user(data: MaybeCustomClient, config: web::Data<Config>) -> HttpResponse {
+    let user = crate::user::get_account(&data.client).await;
+    let user = match user {
+        Err(e) if e.to_string().contains("Session expired") && !data.is_custom => {
+            let new_client = match renew_session(&data, &config).await {
+                Ok(client) => client,
+                Err(e) => return ApiError::response(StatusCode::UNAUTHORIZED, "AUTH_FAILED", e),
+            };
+
+            info!("Session renewed, retrying to get user info...");
+            crate::user::get_account(&new_client).await
+        }
+        user => user,
+    };
+
+    let user = match user {
+        Ok(user) => user,
+        Err(e) => return ApiError::from_ygg_error(e.as_ref()),
+    };
+
+    let mut response = HttpResponse::Ok();
+    if let Some(cookies) = data.cookies_header {
+        response.insert_header(("X-Session-Cookies", cookies));
+    }
+    response.json(UserResponse::from(user))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/status",
+    tag = "status",
+    responses((status = 200, description = "Status of the YGG connection", body = StatusResponse))
+)]
+#[get("/status")]
+pub async fn v1_status(data: MaybeCustomClient, config: web::Data<Config>) -> HttpResponse {
+    let status = get_status(&data, &config).await;
+
+    let mut response = HttpResponse::Ok();
+    if let Some(cookies) = data.cookies_header {
+        response.insert_header(("X-Session-Cookies", cookies));
+    }
+    response.json(status)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/openapi.json",
+    tag = "meta",
+    responses((status = 200, description = "OpenAPI 3.1 document of this API"))
+)]
+#[get("/openapi.json")]
+pub async fn v1_openapi() -> HttpResponse {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openapi() {
+        let doc = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        for path in [
+            "/api/v1/search",
+            "/api/v1/categories",
+            "/api/v1/user",
+            "/api/v1/status",
+            "/api/v1/openapi.json",
+        ] {
+            assert!(doc["paths"][path]["get"].is_object(), "{} missing", path);
+        }
+
+        let schemas = &doc["components"]["schemas"];
+        for schema in [
+            "ApiError",
+            "TorrentItem",
+            "SearchResponse",
+            "StatusResponse",
+        ] {
+            assert!(schemas[schema].is_object(), "{} missing", schema);
+        }
+        let params = doc["paths"]["/api/v1/search"]["get"]["parameters"]
+            .as_array()
+            .unwrap();
+        assert_eq!(params.len(), SEARCH_QUERY_PARAMS.len());
+        let season = params.iter().find(|p| p["name"] == "season").unwrap();
+        assert_eq!(season["in"], "query");
+        assert_eq!(season["schema"]["type"], "integer");
+    }
+
+    #[test]
+    fn test_torrent_item() {
+        let torrent = Torrent {
+            category_id: 2183,
+            name: "Moana.2.2024.MULTi.1080p.WEB-DL.H265".to_string(),
+            id: 1234567,
+            comments_count: 43,
+            age_stamp: 1738044926,
+            size: 3189013217,
+            completed: 15624,
+            seed: 933,
+            info_url: "/torrent/info/film/1234567-moana".to_string(),
+            link: "https://ygg.example/torrent/film/1234567-moana".to_string(),
+            release: ReleaseInfo::parse("Moana.2.2024.MULTi.1080p.WEB-DL.H265"),
+            ..Default::default()
+        };
+        let item = serde_json::to_value(TorrentItem::new(torrent, true)).unwrap();
+        assert_eq!(item["download"], "/torrent/1234567");
+        assert_eq!(item["resolution"], "1080p");
+        assert_eq!(item["year"], 2024);
+        assert!(item.get("infohash").is_none());
+    }
+}
@@ -0,0 +1,166 @@
+use crate::categories::Category;
+use crate::parser::Torrent;
+use crate::torznab::{escape, parent_category, pub_date};
+
+/// Renders torrents as a plain RSS 2.0 feed, for generic RSS readers and the RSS
+/// downloaders of torrent clients. Enclosures point to the `/torrent/{id}` endpoint.
+pub fn feed_xml(
+    torrents: &[Torrent],
+    categories: &[Category],
+    title: &str,
+    base_url: &str,
+    feed_url: &str,
+) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str("  <channel>\n");
+    xml.push_str(&format!("    <title>{}</title>\n", escape(title)));
+    xml.push_str("    <description>Derniers torrents YGG</description>\n");
+    xml.push_str(&format!("    <link>{}/</link>\n", escape(base_url)));
+    xml.push_str(&format!(
+        "    <atom:link href=\"{}\" rel=\"self\" type=\"application/rss+xml\" />\n",
+        escape(feed_url)
+    ));
+    xml.push_str("    <language>fr</language>\n");
+    if let Some(last) = torrents.iter().map(|t| t.age_stamp).max() {
+        xml.push_str(&format!(
+            "    <lastBuildDate>{}</lastBuildDate>\n",
+            pub_date(last)
+        ));
+    }
+    for torrent in torrents {
+        xml.push_str(&item_xml(torrent, categories, base_url));
+    }
+    xml.push_str("  </channel>\n</rss>\n");
+    xml
+}
+
+/// Title of the feed, from the search terms and the category names.
+pub fn feed_title(
+    name: &str,
+    category: Option<usize>,
+    sub_category: Option<usize>,
+    categories: &[Category],
+) -> String {
+    let mut parts = vec!["Ygégé".to_string()];
+    for id in [category, sub_category].into_iter().flatten() {
+        if let Some(name) = category_name(categories, id) {
+            parts.push(name);
+        }
+    }
+    if !name.is_empty() {
+        parts.push(name.to_string());
+    }
+    parts.join(" - ")
+}
+
+fn item_xml(torrent: &Torrent, categories: &[Category], base_url: &str) -> String {
+    let download = format!("{}/torrent/{}", base_url, torrent.id);
+    let link = match torrent.link.is_empty() {
+        true => download.clone(),
+        false => torrent.link.clone(),
+    };
+    let description = format!(
+        "Taille : {} - Seeders : {} - Leechers : {} - Complétés : {}",
+        human_size(torrent.size),
+        torrent.seed,
+        torrent.leech,
+        torrent.completed
+    );
+
+    let mut xml = String::from("    <item>\n");
+    xml.push_str(&format!("      <title>{}</title>\n", escape(&torrent.name)));
+    xml.push_str(&format!("      <link>{}</link>\n", escape(&link)));
+    xml.push_str(&format!(
+        "      <guid isPermaLink=\"false\">ygg-{}</guid>\n",
+        torrent.id
+    ));
+    xml.push_str(&format!(
+        "      <pubDate>{}</pubDate>\n",
+        pub_date(torrent.age_stamp)
+    ));
+    xml.push_str(&format!(
+        "      <description>{}</description>\n",
+        escape(&description)
+    ));
+    if let Some(name) = category_name(categories, torrent.category_id) {
+        xml.push_str(&format!("      <category>{}</category>\n", escape(&name)));
+    }
+    xml.push_str(&format!(
+        "      <enclosure url=\"{}\" length=\"{}\" type=\"application/x-bittorrent\" />\n",
+        escape(&download),
+        torrent.size
+    ));
+    xml.push_str("    </item>\n");
+    xml
+}
+
+fn category_name(categories: &[Category], id: usize) -> Option<String> {
+    let siblings = match parent_category(categories, id) {
+        Some(parent) => {
+            &categories
+                .iter()
+                .find(|cat| cat.id == parent)?
+                .sub_categories
+        }
+        None => categories,
+    };
+    siblings
+        .iter()
+        .find(|cat| cat.id == id)
+        .map(|cat| cat.name.clone())
+}
+
+fn human_size(size: u64) -> String {
+    let units = ["o", "Ko", "Mo", "Go", "To"];
+    let mut value = size as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < units.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} {}", size, units[0]),
+        _ => format!("{:.2} {}", value, units[unit]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{categories, feed_torrent};
+
+    #[test]
+    fn test_feed_xml() {
+        let xml = feed_xml(
+            &[feed_torrent()],
+            &categories(),
+            "Ygégé - Film",
+            "http://localhost:8715",
+            "http://localhost:8715/rss?category=2183&apikey=a&b",
+        );
+        assert!(xml.contains("<rss version=\"2.0\""));
+        assert!(xml.contains("<title>Ygégé - Film</title>"));
+        assert!(
+            xml.contains("href=\"http://localhost:8715/rss?category=2183&amp;apikey=a&amp;b\"")
+        );
+        assert!(xml.contains("<title>Vaiana &amp; co &lt;2016&gt;</title>"));
+        assert!(xml.contains("<link>https://ygg.example/torrent/film/1234-vaiana</link>"));
+        assert!(xml.contains("<guid isPermaLink=\"false\">ygg-1234</guid>"));
+        assert!(xml.contains("<pubDate>Tue, 28 Jan 2025 06:15:26 +0000</pubDate>"));
+        assert!(xml.contains("<category>Film</category>"));
+        assert!(xml.contains(
+            "<enclosure url=\"http://localhost:8715/torrent/1234\" length=\"3189013217\" type=\"application/x-bittorrent\" />"
+        ));
+        assert!(xml.contains("Taille : 2.97 Go - Seeders : 10"));
+    }
+
+    #[test]
+    fn test_feed_title() {
+        assert_eq!(
+            feed_title("vaiana", Some(2145), Some(2183), &categories()),
+            "Ygégé - Film/Vidéo - Film - vaiana"
+        );
+        assert_eq!(feed_title("", None, None, &categories()), "Ygégé");
+    }
+}
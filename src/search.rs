@@ -3,13 +3,20 @@ use crate::parser::Torrent;
 use crate::rate_limiter::RateLimiter;
 use crate::utils::check_session_expired;
 use crate::ygg_client::YggClient;
-use crate::{DOMAIN, parser};
+use crate::{DOMAIN, cache, parser};
+use std::collections::HashSet;
 use std::str::FromStr;
 use std::sync::OnceLock;
 use urlencoding::{decode, encode};
 
 static RATE_LIMITER: OnceLock<RateLimiter> = OnceLock::new();
 
+/// Number of results returned by one YGG search page.
+pub const PAGE_SIZE: usize = 50;
+
+/// Largest `limit` accepted by the paging layer (10 YGG pages).
+pub const MAX_LIMIT: usize = 500;
+
 pub(crate) fn get_rate_limiter() -> &'static RateLimiter {
     RATE_LIMITER.get_or_init(|| RateLimiter::default())
 }
@@ -17,6 +24,7 @@ pub(crate) fn get_rate_limiter() -> &'static RateLimiter {
 pub async fn search(
     client: &YggClient,
     name: &str,
+    fields: &SearchFields,
     offset: Option<usize>,
     category: Option<usize>,
     sub_category: Option<usize>,
@@ -42,9 +50,22 @@ pub async fn search(
         name, offset, category, sub_category, sort, order
     );
 
+    let url = build_query_url(
+        name.as_str(),
+        fields,
+        offset,
+        category,
+        sub_category,
+        sort,
+        order,
+    )?;
+    if let Some(torrents) = cache::lookup(&url) {
+        debug!("Found {} torrents in cache", torrents.len());
+        return Ok(filter_ban_words(torrents, ban_words.as_deref()));
+    }
+
     let _guard = get_rate_limiter().acquire().await;
 
-    let url = build_query_url(name.as_str(), offset, category, sub_category, sort, order)?;
     let start = std::time::Instant::now();
     let response = client.get(&url).await?;
 
@@ -54,18 +75,9 @@ pub async fn search(
 
     debug!("Search response: {}", response.status);
     let torrents = parser::extract_torrents(&response.body)?;
-    let torrents = if let Some(ban_words) = ban_words {
-        torrents
-            .into_iter()
-            .filter(|torrent| {
-                !ban_words
-                    .iter()
-                    .any(|word| torrent.name.to_lowercase().contains(&word.to_lowercase()))
-            })
-            .collect()
-    } else {
-        torrents
-    };
+    cache::store(&url, &torrents);
+    crate::torrent::remember_categories(&torrents);
+    let torrents = filter_ban_words(torrents, ban_words.as_deref());
     let stop = std::time::Instant::now();
     debug!(
         "Found {} torrents in {:?}",
@@ -75,6 +87,147 @@ pub async fn search(
     Ok(torrents)
 }
 
+/// Returns the `[offset, offset + limit)` window of the results, fetching as many
+/// YGG pages as needed. Without a limit, `offset` is passed as is to YGG and a
+/// single page is returned.
+pub async fn search_window(
+    client: &YggClient,
+    name: &str,
+    fields: &SearchFields,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    category: Option<usize>,
+    sub_category: Option<usize>,
+    sort: Option<Sort>,
+    order: Option<Order>,
+    ban_words: Option<Vec<String>>,
+    quote_search: bool,
+) -> Result<Vec<Torrent>, Box<dyn std::error::Error>> {
+    let Some(limit) = limit else {
+        return search(
+            client,
+            name,
+            fields,
+            offset,
+            category,
+            sub_category,
+            sort,
+            order,
+            ban_words,
+            quote_search,
+        )
+        .await;
+    };
+
+    // Ban words are applied to the window, so that page boundaries stay aligned
+    let torrents = collect_window(offset.unwrap_or(0), limit, |page| {
+        search(
+            client,
+            name,
+            fields,
+            Some(page).filter(|page| *page > 0),
+            category,
+            sub_category,
+            sort,
+            order,
+            None,
+            quote_search,
+        )
+    })
+    .await?;
+    Ok(filter_ban_words(torrents, ban_words.as_deref()))
+}
+
+/// Fetches consecutive pages (by result offset, as YGG expects) until the window
+/// is filled or the last page is reached, dropping duplicated torrents.
+async fn collect_window<F, Fut>(
+    offset: usize,
+    limit: usize,
+    mut fetch_page: F,
+) -> Result<Vec<Torrent>, Box<dyn std::error::Error>>
+where
+    F: FnMut(usize) -> Fut,
+    Fut: Future<Output = Result<Vec<Torrent>, Box<dyn std::error::Error>>>,
+{
+    let limit = limit.min(MAX_LIMIT);
+    let mut page = offset - offset % PAGE_SIZE;
+    let mut skip = offset % PAGE_SIZE;
+    let mut seen = HashSet::new();
+    let mut torrents = Vec::new();
+
+    while torrents.len() < limit {
+        let results = fetch_page(page).await?;
+        let count = results.len();
+        debug!("Page {} returned {} results", page / PAGE_SIZE, count);
+
+        torrents.extend(
+            results
+                .into_iter()
+                .skip(skip)
+                .filter(|torrent| seen.insert(torrent.id)),
+        );
+        skip = 0;
+
+        if count < PAGE_SIZE {
+            break;
+        }
+        page += PAGE_SIZE;
+    }
+
+    torrents.truncate(limit);
+    Ok(torrents)
+}
+
+fn filter_ban_words(torrents: Vec<Torrent>, ban_words: Option<&[String]>) -> Vec<Torrent> {
+    match ban_words {
+        Some(ban_words) => torrents
+            .into_iter()
+            .filter(|torrent| {
+                !ban_words
+                    .iter()
+                    .any(|word| torrent.name.to_lowercase().contains(&word.to_lowercase()))
+            })
+            .collect(),
+        None => torrents,
+    }
+}
+
+/// Fields of the YGG search engine besides `name`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchFields {
+    pub uploader: Option<String>,
+    pub description: Option<String>,
+    pub file: Option<String>,
+    /// Sub-category options, as YGG `(parameter, value id)` pairs
+    pub options: Vec<(String, String)>,
+}
+
+impl SearchFields {
+    pub fn is_empty(&self) -> bool {
+        self.uploader.is_none()
+            && self.description.is_none()
+            && self.file.is_none()
+            && self.options.is_empty()
+    }
+
+    /// `(parameter, value)` pairs of the fields that are set.
+    pub fn params(&self) -> Vec<(&str, &str)> {
+        [
+            ("uploader", &self.uploader),
+            ("description", &self.description),
+            ("file", &self.file),
+        ]
+        .into_iter()
+        .filter_map(|(field, value)| value.as_deref().map(|value| (field, value)))
+        .chain(
+            self.options
+                .iter()
+                .map(|(param, value)| (param.as_str(), value.as_str())),
+        )
+        .collect()
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum Sort {
     Name,
@@ -83,6 +236,8 @@ pub enum Sort {
     PublishDate,
     Completed,
     Leech,
+    /// Ranked locally against the searched titles (see `relevance::score`)
+    Relevance,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -99,6 +254,8 @@ impl Sort {
             Sort::PublishDate => "publish_date",
             Sort::Completed => "completed",
             Sort::Leech => "leech",
+            // Not a YGG column: pages are fetched by seeders, then ranked
+            Sort::Relevance => "seed",
         }
     }
 }
@@ -123,6 +280,7 @@ impl FromStr for Sort {
             "publish_date" => Ok(Sort::PublishDate),
             "completed" => Ok(Sort::Completed),
             "leech" => Ok(Sort::Leech),
+            "relevance" => Ok(Sort::Relevance),
             _ => Err(format!("Valeur de tri invalide : {}", s)),
         }
     }
@@ -140,8 +298,35 @@ impl FromStr for Order {
     }
 }
 
+/// Torznab search functions (the `t` parameter of `/api`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchMode {
+    #[default]
+    Search,
+    Tv,
+    Movie,
+    Music,
+    Book,
+}
+
+impl FromStr for SearchMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "search" => Ok(SearchMode::Search),
+            "tvsearch" => Ok(SearchMode::Tv),
+            "movie" => Ok(SearchMode::Movie),
+            "music" => Ok(SearchMode::Music),
+            "book" => Ok(SearchMode::Book),
+            _ => Err(format!("Mode de recherche invalide : {}", s)),
+        }
+    }
+}
+
 fn build_query_url(
     name: &str,
+    fields: &SearchFields,
     offset: Option<usize>,
     category: Option<usize>,
     sub_category: Option<usize>,
@@ -154,6 +339,9 @@ fn build_query_url(
     drop(domain_lock);
 
     let mut url = format!("https://{domain}/engine/search?name={}", encode(&name));
+    for (field, value) in fields.params() {
+        url.push_str(&format!("&{}={}", encode(field), encode(value)));
+    }
     if let Some(offset) = offset {
         url.push_str(&format!("&page={offset}"));
     }
@@ -195,6 +383,59 @@ fn get_category_pair(category: usize) -> Option<(String, String)> {
 mod tests {
     use super::*;
     use crate::domain::get_ygg_domain;
+    use crate::fixtures::torrent;
+
+    /// Pages over 120 fake results, where the second page repeats the last
+    /// result of the first one (as when a new upload shifts the pages).
+    async fn fake_page(page: usize) -> Result<Vec<Torrent>, Box<dyn std::error::Error>> {
+        let start = match page {
+            0 => 0,
+            _ => page - 1,
+        };
+        Ok((start..(start + PAGE_SIZE).min(120)).map(torrent).collect())
+    }
+
+    #[test]
+    fn test_search_fields() {
+        let fields = SearchFields {
+            uploader: Some("Team".to_string()),
+            file: Some("sample.mkv".to_string()),
+            options: vec![("option_langue:multiple[]".to_string(), "4".to_string())],
+            ..Default::default()
+        };
+        assert_eq!(
+            fields.params(),
+            vec![
+                ("uploader", "Team"),
+                ("file", "sample.mkv"),
+                ("option_langue:multiple[]", "4")
+            ]
+        );
+        assert!(SearchFields::default().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_collect_window() {
+        let ids = |torrents: Vec<Torrent>| torrents.iter().map(|t| t.id).collect::<Vec<_>>();
+
+        let window = collect_window(0, 10, fake_page).await.unwrap();
+        assert_eq!(ids(window), (0..10).collect::<Vec<_>>());
+
+        let window = collect_window(40, 20, fake_page).await.unwrap();
+        assert_eq!(ids(window), (40..60).collect::<Vec<_>>());
+
+        let window = collect_window(90, 100, fake_page).await.unwrap();
+        assert_eq!(ids(window), (89..120).collect::<Vec<_>>());
+
+        let mut pages = Vec::new();
+        collect_window(75, 50, |page| {
+            pages.push(page);
+            fake_page(page)
+        })
+        .await
+        .unwrap();
+        assert_eq!(pages, vec![50, 100]);
+    }
 
     #[tokio::test]
     async fn test_build_query_url() {
@@ -208,6 +449,7 @@ mod tests {
 
         let url = build_query_url(
             "Vaiana",
+            &SearchFields::default(),
             None,
             None,
             None,
@@ -0,0 +1,150 @@
+use crate::DOMAIN;
+use crate::cache;
+use crate::categories::Category;
+use crate::parser::{Torrent, extract_all_torrents};
+use crate::search::get_rate_limiter;
+use crate::utils::check_session_expired;
+use crate::ygg_client::YggClient;
+use std::str::FromStr;
+
+/// Periods of the YGG top pages (`/top/day`, `/top/week`, `/top/month`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopPeriod {
+    Day,
+    Week,
+    Month,
+}
+
+impl TopPeriod {
+    pub fn as_str(&self) -> &str {
+        match self {
+            TopPeriod::Day => "day",
+            TopPeriod::Week => "week",
+            TopPeriod::Month => "month",
+        }
+    }
+}
+
+impl FromStr for TopPeriod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "day" => Ok(TopPeriod::Day),
+            "week" => Ok(TopPeriod::Week),
+            "month" => Ok(TopPeriod::Month),
+            _ => Err(format!("Invalid period: {} (day, week or month)", s)),
+        }
+    }
+}
+
+/// Scrapes the top torrents of a period. Pages are cached like search pages.
+pub async fn get_top(
+    client: &YggClient,
+    period: TopPeriod,
+) -> Result<Vec<Torrent>, Box<dyn std::error::Error>> {
+    let domain = {
+        let domain_lock = DOMAIN.lock()?;
+        domain_lock.clone()
+    };
+
+    let url = format!("https://{}/top/{}", domain, period.as_str());
+    if let Some(torrents) = cache::lookup(&url) {
+        debug!("Found {} top torrents in cache", torrents.len());
+        return Ok(torrents);
+    }
+
+    let _guard = get_rate_limiter().acquire().await;
+    let response = client.get(&url).await?;
+
+    if check_session_expired(response.status, &response.url) {
+        return Err("Session expired".into());
+    }
+    if !(200..300).contains(&response.status) {
+        return Err(format!("Failed to get top torrents: {}", response.status).into());
+    }
+
+    let torrents = extract_all_torrents(&response.body)?;
+    cache::store(&url, &torrents);
+    crate::torrent::remember_categories(&torrents);
+    Ok(torrents)
+}
+
+/// Keeps the torrents of a category, given as a main category or a sub-category.
+pub fn filter_category(
+    torrents: Vec<Torrent>,
+    category: usize,
+    categories: &[Category],
+) -> Vec<Torrent> {
+    let sub_categories: Vec<usize> = categories
+        .iter()
+        .filter(|cat| cat.id == category)
+        .flat_map(|cat| cat.sub_categories.iter().map(|sub| sub.id))
+        .collect();
+    torrents
+        .into_iter()
+        .filter(|t| t.category_id == category || sub_categories.contains(&t.category_id))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    const TOP_PAGE: &str = r#"<html><body>
+<h2>Film/Vidéo</h2>
+<div class="table-responsive"><table class="table"><tbody>
+<tr>
+  <td><div class="hidden">2183</div></td>
+  <td><a id="torrent_name" href="https://ygg.example/torrent/filmvideo/film/1234-moana">Moana.2.2024.MULTi.1080p.WEB-DL.H265</a></td>
+  <td><a id="get_nfo" target="1234"></a></td>
+  <td>12</td>
+  <td><div class="hidden">1738044926</div>il y a 2 jours</td>
+  <td>2.97Go</td>
+  <td>1500</td>
+  <td>800</td>
+  <td>20</td>
+</tr>
+</tbody></table></div>
+<h2>Audio</h2>
+<div class="table-responsive"><table class="table"><tbody>
+<tr>
+  <td><div class="hidden">2148</div></td>
+  <td><a id="torrent_name" href="https://ygg.example/torrent/audio/musique/5678-album">Album.2024.FLAC</a></td>
+  <td><a id="get_nfo" target="5678"></a></td>
+  <td>0</td>
+  <td><div class="hidden">1738000000</div></td>
+  <td>512.00Mo</td>
+  <td>300</td>
+  <td>150</td>
+  <td>4</td>
+</tr>
+</tbody></table></div>
+</body></html>"#;
+
+    #[test]
+    fn test_top_page() {
+        let torrents = extract_all_torrents(TOP_PAGE).unwrap();
+        assert_eq!(torrents.len(), 2);
+        assert_eq!(torrents[0].id, 1234);
+        assert_eq!(torrents[0].category_id, 2183);
+        assert_eq!(torrents[0].seed, 800);
+        assert_eq!(
+            torrents[0].info_url,
+            "/torrent/info/filmvideo/film/1234-moana"
+        );
+        assert_eq!(torrents[1].id, 5678);
+
+        let categories = fixtures::categories();
+        let films = filter_category(torrents.clone(), 2145, &categories);
+        assert_eq!(films.len(), 1);
+        assert_eq!(filter_category(torrents, 2148, &categories).len(), 1);
+    }
+
+    #[test]
+    fn test_period() {
+        assert_eq!("week".parse::<TopPeriod>(), Ok(TopPeriod::Week));
+        assert!("year".parse::<TopPeriod>().is_err());
+    }
+}
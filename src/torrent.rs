@@ -0,0 +1,590 @@
+use crate::DOMAIN;
+use crate::auth::KEY;
+use crate::parser::{Torrent, human_readable_size_to_bytes};
+use crate::torrent_cache;
+use crate::utils::check_session_expired;
+use crate::ygg_client::YggClient;
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+use tokio::time::{Duration, sleep};
+
+/// Host of the YGG tracker, announces go to `{TRACKER_URL}/{passkey}/announce`.
+pub const TRACKER_URL: &str = "http://connect.maxp2p.org:8080";
+
+/// Number of torrents remembered in each of the `KnownTorrents` maps.
+const MAX_KNOWN_TORRENTS: usize = 10_000;
+
+/// Infohashes (and release names) learned from detail pages and .torrent files,
+/// by YGG torrent id.
+static KNOWN_INFOHASHES: Mutex<KnownTorrents<(String, String)>> =
+    Mutex::new(KnownTorrents::new(MAX_KNOWN_TORRENTS));
+
+/// Sub-categories of the torrents seen in search and top results, by YGG torrent id.
+static KNOWN_CATEGORIES: Mutex<KnownTorrents<usize>> =
+    Mutex::new(KnownTorrents::new(MAX_KNOWN_TORRENTS));
+
+/// Map of what was learned about torrents, by YGG torrent id. Once full, the
+/// torrents remembered first are dropped.
+struct KnownTorrents<T> {
+    max_entries: usize,
+    entries: BTreeMap<usize, T>,
+    order: VecDeque<usize>,
+}
+
+impl<T: Clone> KnownTorrents<T> {
+    const fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries,
+            entries: BTreeMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    fn get(&self, id: usize) -> Option<T> {
+        self.entries.get(&id).cloned()
+    }
+
+    fn insert(&mut self, id: usize, value: T) {
+        if self.entries.insert(id, value).is_some() {
+            return;
+        }
+        self.order.push_back(id);
+        while self.order.len() > self.max_entries {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+pub fn remember_infohash(id: usize, infohash: &str, name: &str) {
+    if let Ok(mut known) = KNOWN_INFOHASHES.lock() {
+        known.insert(id, (infohash.to_lowercase(), name.to_string()));
+    }
+}
+
+/// Returns the infohash and release name of a torrent, if already known.
+pub fn known_infohash(id: usize) -> Option<(String, String)> {
+    KNOWN_INFOHASHES.lock().ok()?.get(id)
+}
+
+pub fn remember_categories(torrents: &[Torrent]) {
+    if let Ok(mut known) = KNOWN_CATEGORIES.lock() {
+        for torrent in torrents {
+            known.insert(torrent.id, torrent.category_id);
+        }
+    }
+}
+
+/// Returns the sub-category of a torrent, if it was seen in search results.
+pub fn known_category(id: usize) -> Option<usize> {
+    KNOWN_CATEGORIES.lock().ok()?.get(id)
+}
+
+/// Builds a magnet URI announcing to the YGG tracker with the account passkey.
+/// Returns None until the passkey has been scraped from the account page.
+pub fn magnet_uri(infohash: &str, name: &str) -> Option<String> {
+    let passkey = KEY.get().filter(|key| !key.is_empty())?;
+    let announce = format!("{}/{}/announce", TRACKER_URL, passkey);
+    Some(build_magnet_uri(infohash, name, &announce))
+}
+
+pub fn build_magnet_uri(infohash: &str, name: &str, announce: &str) -> String {
+    format!(
+        "magnet:?xt=urn:btih:{}&dn={}&tr={}",
+        infohash.to_lowercase(),
+        urlencoding::encode(name),
+        urlencoding::encode(announce)
+    )
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct TorrentDetails {
+    pub id: usize,
+    pub name: String,
+    pub link: String,
+    pub description: String,
+    pub description_html: String,
+    pub uploader: Option<String>,
+    pub upload_date: Option<String>,
+    pub size: u64,
+    pub infohash: Option<String>,
+    pub files: Vec<TorrentFile>,
+    pub imdb: Option<String>,
+    pub tmdb: Option<String>,
+    pub seed: usize,
+    pub leech: usize,
+    pub completed: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TorrentFile {
+    pub name: String,
+    pub size: u64,
+}
+
+/// Fetches the YGG detail page of a torrent. `path` is the part of the YGG
+/// url after `/torrent/`, as exposed by the `info_url` search field.
+pub async fn get_torrent_details(
+    client: &YggClient,
+    path: &str,
+) -> Result<TorrentDetails, Box<dyn std::error::Error>> {
+    debug!("Fetching torrent details for {}", path);
+
+    let domain = {
+        let domain_lock = DOMAIN.lock()?;
+        domain_lock.clone()
+    };
+
+    let url = format!(
+        "https://{}/torrent/{}",
+        domain,
+        path.trim_start_matches('/')
+    );
+    let response = client.get(&url).await?;
+
+    if check_session_expired(response.status, &response.url) {
+        return Err("Session expired".into());
+    }
+
+    if response.status == 404 {
+        return Err("Torrent not found".into());
+    }
+
+    if !(200..300).contains(&response.status) {
+        return Err(format!("Failed to fetch torrent details: {}", response.status).into());
+    }
+
+    let mut details = parse_torrent_details(&response.body)?;
+    details.link = url;
+    if details.id == 0 {
+        details.id = id_from_path(path).ok_or("Unable to find torrent id")?;
+    }
+    if let Some(infohash) = &details.infohash {
+        remember_infohash(details.id, infohash, &details.name);
+    }
+
+    // The file list is loaded by the page through an ajax call
+    let files_url = format!("https://{}/engine/get_files?torrent={}", domain, details.id);
+    match client.get(&files_url).await {
+        Ok(response) if (200..300).contains(&response.status) => {
+            details.files = parse_files(&response.body)?;
+        }
+        Ok(response) => warn!(
+            "Failed to fetch file list of torrent {}: {}",
+            details.id, response.status
+        ),
+        Err(e) => warn!("Failed to fetch file list of torrent {}: {}", details.id, e),
+    }
+
+    Ok(details)
+}
+
+/// Downloads the signed .torrent file of a torrent, waiting for the YGG download
+/// timer unless turbo mode is enabled. With `use_cache`, files in the torrent cache
+/// are served without contacting YGG. Cached files carry the passkey of the shared
+/// account, so requests made with custom cookies must not use it.
+pub async fn download_torrent_file(
+    client: &YggClient,
+    id: usize,
+    turbo_enabled: bool,
+    use_cache: bool,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let cached = match use_cache {
+        true => torrent_cache::lookup(id).await,
+        false => None,
+    };
+    if let Some(bytes) = cached {
+        debug!("Found torrent {} in the torrent cache", id);
+        return Ok(bytes);
+    }
+
+    let domain = {
+        let domain_lock = DOMAIN.lock()?;
+        domain_lock.clone()
+    };
+
+    // Request token
+    let url = format!("https://{}/engine/start_download_timer", domain);
+    let body = format!("torrent_id={}", id);
+
+    debug!("Request download token {} {}", url, body);
+
+    let response = client.post_form(&url, &body).await?;
+
+    if !(200..300).contains(&response.status) {
+        return Err(format!("Failed to get token: {}", response.status).into());
+    }
+
+    let json: Value = serde_json::from_str(&response.body)?;
+    debug!("Response {}", json);
+
+    let token = json
+        .get("token")
+        .and_then(|h| h.as_str())
+        .ok_or("Token not found in start_download_timer response")?;
+
+    if !turbo_enabled {
+        debug!("Wait 30 secs...");
+        sleep(Duration::from_secs(30)).await;
+        debug!("Wait is over");
+    }
+
+    // Request signed torrent file
+    let url = format!(
+        "https://{}/engine/download_torrent?id={}&token={}",
+        domain, id, token
+    );
+    debug!("download URL {}", url);
+
+    let response = client.get_bytes(&url).await?;
+    let (status, bytes) = (response.status, response.bytes);
+
+    if !(200..300).contains(&status) {
+        if status == 302 {
+            return match crate::utils::get_remaining_downloads(client).await {
+                Ok(0) => {
+                    error!("No remaining downloads");
+                    Err("No remaining downloads".into())
+                }
+                Ok(n) => {
+                    warn!(
+                        "Failed to download torrent, but you have {} remaining downloads, might be caused by an insufficient ratio.",
+                        n
+                    );
+                    Err("Failed to download torrent, but you have remaining downloads.".into())
+                }
+                Err(e) => {
+                    error!("Error while checking remaining downloads: {}", e);
+                    Err("Failed to download torrent and check remaining downloads.".into())
+                }
+            };
+        }
+        return Err(format!(
+            "Failed to get torrent file: {} {}",
+            status,
+            String::from_utf8_lossy(&bytes)
+        )
+        .into());
+    }
+
+    if use_cache {
+        torrent_cache::store(id, &bytes).await;
+    }
+    Ok(bytes)
+}
+
+fn id_from_path(path: &str) -> Option<usize> {
+    path.trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .and_then(|slug| slug.split('-').next())
+        .and_then(|id| id.parse().ok())
+}
+
+fn stat_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?i)(\d+)\s*(seeders?|leechers?|compl[ée]t)").unwrap())
+}
+
+fn imdb_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"imdb\.com/title/(tt\d+)").unwrap())
+}
+
+fn tmdb_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"themoviedb\.org/(?:movie|tv)/(\d+)").unwrap())
+}
+
+fn clean_text(element: ElementRef) -> String {
+    element
+        .text()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn parse_torrent_details(body: &str) -> Result<TorrentDetails, Box<dyn std::error::Error>> {
+    let document = Html::parse_document(body);
+    let mut details = TorrentDetails::default();
+
+    let table = document
+        .select(&Selector::parse("table.informations")?)
+        .next()
+        .ok_or("Torrent informations table not found")?;
+
+    let td_selector = Selector::parse("td")?;
+    for row in table.select(&Selector::parse("tr")?) {
+        let cells: Vec<_> = row.select(&td_selector).collect();
+
+        if let [key_cell, value_cell, ..] = cells.as_slice() {
+            let key = clean_text(*key_cell);
+            let value = clean_text(*value_cell);
+
+            if key.contains("Nom du torrent") {
+                details.name = value;
+            } else if key.contains("Info Hash") {
+                details.infohash = Some(value.to_lowercase());
+            } else if key.contains("Taille") {
+                details.size = human_readable_size_to_bytes(&value).unwrap_or_default();
+            } else if (key.contains("Upload") && key.contains("par")) || key.contains("Uploader") {
+                details.uploader = Some(value).filter(|v| !v.is_empty());
+            } else if key.contains("Upload") || key.contains("Date") {
+                details.upload_date = Some(parse_upload_date(&value));
+            }
+        }
+    }
+
+    if details.name.is_empty() {
+        details.name = document
+            .select(&Selector::parse("#title h1")?)
+            .next()
+            .map(clean_text)
+            .unwrap_or_default();
+    }
+
+    details.id = document
+        .select(&Selector::parse("#get_nfo, [data-torrent-id]")?)
+        .next()
+        .and_then(|e| {
+            e.value()
+                .attr("target")
+                .or(e.value().attr("data-torrent-id"))
+        })
+        .and_then(|id| id.parse().ok())
+        .unwrap_or_default();
+
+    let description = document
+        .select(&Selector::parse(
+            "#description, section.default div.default",
+        )?)
+        .next();
+    if let Some(description) = description {
+        details.description_html = description.inner_html().trim().to_string();
+        details.description = description
+            .text()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+
+    for link in document.select(&Selector::parse("a[href]")?) {
+        let href = link.value().attr("href").unwrap_or_default();
+        if details.imdb.is_none() {
+            details.imdb = imdb_regex().captures(href).map(|caps| caps[1].to_string());
+        }
+        if details.tmdb.is_none() {
+            details.tmdb = tmdb_regex().captures(href).map(|caps| caps[1].to_string());
+        }
+    }
+
+    // Counts come from the stats row only, the description may mention seeders too
+    let in_description = |row: &ElementRef| {
+        description
+            .is_some_and(|description| row.ancestors().any(|node| node.id() == description.id()))
+    };
+    let stats = document
+        .select(&Selector::parse("tr")?)
+        .filter(|row| !in_description(row))
+        .map(clean_text)
+        .find(|text| stat_regex().is_match(text))
+        .unwrap_or_default();
+    for caps in stat_regex().captures_iter(&stats) {
+        let count = caps[1].parse().unwrap_or_default();
+        match caps[2].to_lowercase().chars().next() {
+            Some('s') => details.seed = count,
+            Some('l') => details.leech = count,
+            Some('c') => details.completed = count,
+            _ => {}
+        }
+    }
+
+    Ok(details)
+}
+
+/// YGG displays dates as `28/01/2025 07:15`; they are returned as ISO 8601
+/// when possible.
+fn parse_upload_date(value: &str) -> String {
+    let value = value.split('(').next().unwrap_or(value).trim();
+    chrono::NaiveDateTime::parse_from_str(value, "%d/%m/%Y %H:%M")
+        .map(|date| date.format("%Y-%m-%dT%H:%M:00").to_string())
+        .unwrap_or_else(|_| value.to_string())
+}
+
+/// Parses the `/engine/get_files` response, a JSON object whose `html` field
+/// holds a table with one row per file.
+pub fn parse_files(body: &str) -> Result<Vec<TorrentFile>, Box<dyn std::error::Error>> {
+    let html = match serde_json::from_str::<Value>(body) {
+        Ok(json) => json
+            .get("html")
+            .and_then(|h| h.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        Err(_) => body.to_string(),
+    };
+
+    let document = Html::parse_fragment(&html);
+    let td_selector = Selector::parse("td")?;
+    let mut files = Vec::new();
+
+    for row in document.select(&Selector::parse("tr")?) {
+        let cells: Vec<String> = row.select(&td_selector).map(clean_text).collect();
+        let size = cells
+            .iter()
+            .position(|cell| human_readable_size_to_bytes(cell).is_ok());
+
+        if let Some(size_idx) = size {
+            let name = cells
+                .iter()
+                .enumerate()
+                .find(|(idx, cell)| *idx != size_idx && !cell.is_empty())
+                .map(|(_, cell)| cell.clone());
+            if let Some(name) = name {
+                files.push(TorrentFile {
+                    name,
+                    size: human_readable_size_to_bytes(&cells[size_idx])?,
+                });
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DETAILS_PAGE: &str = r#"
+        <html><body>
+        <section class="content">
+            <div id="title"><h1>Moana.2.2024.MULTi.1080p.WEB-DL.H265</h1></div>
+            <table class="informations">
+                <tbody>
+                    <tr><td>Nom du torrent</td><td><h5>Moana.2.2024.MULTi.1080p.WEB-DL.H265</h5></td></tr>
+                    <tr><td>Taille totale</td><td>2.97Go</td></tr>
+                    <tr><td>Info Hash</td><td>0123456789ABCDEF0123456789ABCDEF01234567</td></tr>
+                    <tr><td>Uploadé par</td><td><a href="/profile/1-someone">someone</a></td></tr>
+                    <tr><td>Uploadé le</td><td>28/01/2025 07:15 (il y a 2 mois)</td></tr>
+                </tbody>
+            </table>
+            <a id="get_nfo" target="1234567">NFO</a>
+            <table>
+                <tr><td><strong>933</strong> seeders</td><td><strong>4</strong> leechers</td><td><strong>15624</strong> complétés</td></tr>
+            </table>
+        </section>
+        <section class="default">
+            <div class="default">
+                <p>Synopsis du film</p>
+                <table><tr><td>Merci aux 12 seeders !</td></tr></table>
+                <a href="https://www.imdb.com/title/tt13622970/">IMDb</a>
+                <a href="https://www.themoviedb.org/movie/1241982-moana-2">TMDB</a>
+            </div>
+        </section>
+        </body></html>
+    "#;
+
+    #[test]
+    fn test_parse_torrent_details() {
+        let details = parse_torrent_details(DETAILS_PAGE).unwrap();
+        assert_eq!(details.id, 1234567);
+        assert_eq!(details.name, "Moana.2.2024.MULTi.1080p.WEB-DL.H265");
+        assert_eq!(details.size, 3189013217);
+        assert_eq!(
+            details.infohash.as_deref(),
+            Some("0123456789abcdef0123456789abcdef01234567")
+        );
+        assert_eq!(details.uploader.as_deref(), Some("someone"));
+        assert_eq!(details.upload_date.as_deref(), Some("2025-01-28T07:15:00"));
+        assert_eq!(details.imdb.as_deref(), Some("tt13622970"));
+        assert_eq!(details.tmdb.as_deref(), Some("1241982"));
+        assert_eq!(
+            (details.seed, details.leech, details.completed),
+            (933, 4, 15624)
+        );
+        assert_eq!(
+            details.description,
+            "Synopsis du film\nMerci aux 12 seeders !\nIMDb\nTMDB"
+        );
+    }
+
+    #[test]
+    fn test_parse_files() {
+        let body = r#"{"html":"<table><tr><td>2.97Go<\/td><td>Moana.2.2024.mkv<\/td><\/tr><tr><td>15 ko<\/td><td>Subs\/french.srt<\/td><\/tr><\/table>"}"#;
+        assert_eq!(
+            parse_files(body).unwrap(),
+            vec![
+                TorrentFile {
+                    name: "Moana.2.2024.mkv".to_string(),
+                    size: 3189013217,
+                },
+                TorrentFile {
+                    name: "Subs/french.srt".to_string(),
+                    size: 15360,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_magnet_uri() {
+        assert_eq!(
+            build_magnet_uri(
+                "0123456789ABCDEF0123456789ABCDEF01234567",
+                "Vaiana 2 (2024) MULTi",
+                "http://connect.maxp2p.org:8080/abc/announce"
+            ),
+            "magnet:?xt=urn:btih:0123456789abcdef0123456789abcdef01234567&dn=Vaiana%202%20%282024%29%20MULTi&tr=http%3A%2F%2Fconnect.maxp2p.org%3A8080%2Fabc%2Fannounce"
+        );
+
+        remember_infohash(42, "0123456789ABCDEF0123456789ABCDEF01234567", "Vaiana");
+        assert_eq!(
+            known_infohash(42),
+            Some((
+                "0123456789abcdef0123456789abcdef01234567".to_string(),
+                "Vaiana".to_string()
+            ))
+        );
+        assert_eq!(known_infohash(43), None);
+
+        remember_categories(&[Torrent {
+            id: 42,
+            category_id: 2183,
+            ..Default::default()
+        }]);
+        assert_eq!(known_category(42), Some(2183));
+        assert_eq!(known_category(43), None);
+    }
+
+    #[test]
+    fn test_known_torrents() {
+        let mut known = KnownTorrents::new(2);
+        known.insert(1, "a");
+        known.insert(2, "b");
+        known.insert(1, "c");
+        assert_eq!(known.get(1), Some("c"));
+
+        known.insert(3, "d");
+        assert_eq!(known.get(1), None);
+        assert_eq!(known.get(2), Some("b"));
+        assert_eq!(known.get(3), Some("d"));
+    }
+
+    #[test]
+    fn test_id_from_path() {
+        assert_eq!(
+            id_from_path("filmvidéo/film/1234567-moana-2-2024"),
+            Some(1234567)
+        );
+        assert_eq!(id_from_path("1234567"), Some(1234567));
+        assert_eq!(id_from_path("film/moana"), None);
+    }
+}
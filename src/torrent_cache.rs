@@ -0,0 +1,236 @@
+use crate::bencode::TorrentMeta;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+
+/// Default maximum size of the .torrent cache, in MB.
+pub const DEFAULT_MAX_SIZE: u64 = 256;
+
+/// Default lifetime of a cached .torrent file, in seconds (30 days).
+pub const DEFAULT_MAX_AGE: u64 = 30 * 24 * 3600;
+
+/// Files written between two evictions, each one scanning the whole directory.
+const EVICT_EVERY: usize = 16;
+
+/// Age past which a temporary file is left over from an interrupted write.
+const STALE_TMP_AGE: Duration = Duration::from_secs(600);
+
+static TORRENT_CACHE: OnceLock<TorrentCache> = OnceLock::new();
+
+/// On-disk cache of downloaded .torrent files, stored as `{id}.torrent` in a
+/// directory. Files older than `max_age` are dropped, and the oldest ones are
+/// evicted once the directory grows over `max_size` bytes. Eviction runs at
+/// startup then every `EVICT_EVERY` writes.
+pub struct TorrentCache {
+    dir: PathBuf,
+    max_size: u64,
+    max_age: Duration,
+    writes: AtomicUsize,
+    eviction: Mutex<()>,
+}
+
+impl TorrentCache {
+    pub fn new(dir: PathBuf, max_size: u64, max_age: Duration) -> std::io::Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            max_size,
+            max_age,
+            writes: AtomicUsize::new(0),
+            eviction: Mutex::new(()),
+        })
+    }
+
+    fn path(&self, id: usize) -> PathBuf {
+        self.dir.join(format!("{}.torrent", id))
+    }
+
+    fn is_expired(&self, modified: SystemTime) -> bool {
+        modified.elapsed().is_ok_and(|age| age >= self.max_age)
+    }
+
+    pub fn get(&self, id: usize) -> Option<Vec<u8>> {
+        let path = self.path(id);
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+        if self.is_expired(modified) {
+            let _ = std::fs::remove_file(&path);
+            return None;
+        }
+        std::fs::read(&path).ok()
+    }
+
+    pub fn insert(&self, id: usize, bytes: &[u8]) -> std::io::Result<()> {
+        // Written under a temporary name unique to the write, so a partial file is
+        // never served and two downloads of the same torrent don't mix
+        let write = self.writes.fetch_add(1, Ordering::Relaxed) + 1;
+        let tmp = self.dir.join(format!(
+            "{}.torrent.{}-{}.tmp",
+            id,
+            std::process::id(),
+            write
+        ));
+        std::fs::write(&tmp, bytes)?;
+        std::fs::rename(&tmp, self.path(id))?;
+        if write.is_multiple_of(EVICT_EVERY) {
+            self.evict()?;
+        }
+        Ok(())
+    }
+
+    /// Removes the expired files and the temporary files left by interrupted
+    /// writes, then the oldest files until the cache fits in `max_size`.
+    fn evict(&self) -> std::io::Result<()> {
+        let _guard = self.eviction.lock();
+        let mut files: Vec<(SystemTime, u64, PathBuf)> = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let Ok(metadata) = std::fs::metadata(&path) else {
+                continue;
+            };
+            let modified = metadata.modified()?;
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some("torrent") if self.is_expired(modified) => remove(&path),
+                Some("torrent") => files.push((modified, metadata.len(), path)),
+                Some("tmp") if modified.elapsed().is_ok_and(|age| age >= STALE_TMP_AGE) => {
+                    remove(&path)
+                }
+                _ => {}
+            }
+        }
+
+        let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+        files.sort_by_key(|(modified, _, _)| *modified);
+        for (_, size, path) in files {
+            if total <= self.max_size {
+                break;
+            }
+            remove(&path);
+            total -= size;
+        }
+        Ok(())
+    }
+}
+
+fn remove(path: &Path) {
+    match std::fs::remove_file(path) {
+        Ok(()) => debug!("Evicted {} from the torrent cache", path.display()),
+        Err(e) => warn!("Failed to evict {}: {}", path.display(), e),
+    }
+}
+
+/// Sets up the .torrent cache in `dir`, dropping the files over the limits. Sizes
+/// are in MB, ages in seconds.
+pub fn init_torrent_cache(dir: &str, max_size: u64, max_age: u64) -> std::io::Result<()> {
+    let cache = TorrentCache::new(
+        PathBuf::from(dir),
+        max_size.saturating_mul(1024 * 1024),
+        Duration::from_secs(max_age),
+    )?;
+    cache.evict()?;
+    let _ = TORRENT_CACHE.set(cache);
+    Ok(())
+}
+
+/// Returns the cached .torrent file of a torrent, if any. Disk accesses run on
+/// the blocking thread pool.
+pub async fn lookup(id: usize) -> Option<Vec<u8>> {
+    let cache = TORRENT_CACHE.get()?;
+    tokio::task::spawn_blocking(move || cache.get(id))
+        .await
+        .ok()
+        .flatten()
+}
+
+/// Caches a downloaded .torrent file. Anything that isn't a valid torrent (an
+/// error page served with a 200) is left out.
+pub async fn store(id: usize, bytes: &[u8]) {
+    let Some(cache) = TORRENT_CACHE.get() else {
+        return;
+    };
+    if TorrentMeta::from_bytes(bytes).is_err() {
+        warn!("Not caching torrent {}: invalid .torrent file", id);
+        return;
+    }
+    let bytes = bytes.to_vec();
+    let result = tokio::task::spawn_blocking(move || cache.insert(id, &bytes))
+        .await
+        .map_err(std::io::Error::other)
+        .and_then(|result| result);
+    if let Err(e) = result {
+        warn!("Failed to cache torrent {}: {}", id, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ygege-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_torrent_cache() {
+        let dir = cache_dir("torrent-cache");
+        let cache = TorrentCache::new(dir.clone(), 10, Duration::from_secs(3600)).unwrap();
+        assert_eq!(cache.get(1), None);
+
+        cache.insert(1, b"12345").unwrap();
+        assert_eq!(cache.get(1), Some(b"12345".to_vec()));
+
+        // Over 10 bytes, the oldest file goes
+        std::thread::sleep(Duration::from_millis(20));
+        cache.insert(2, b"678").unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        cache.insert(3, b"9012").unwrap();
+        assert_eq!(cache.get(1), Some(b"12345".to_vec()));
+
+        cache.evict().unwrap();
+        assert_eq!(cache.get(1), None);
+        assert_eq!(cache.get(2), Some(b"678".to_vec()));
+        assert_eq!(cache.get(3), Some(b"9012".to_vec()));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_torrent_cache_tmp() {
+        let dir = cache_dir("torrent-cache-tmp");
+        let cache = TorrentCache::new(dir.clone(), 1024, Duration::from_secs(3600)).unwrap();
+        cache.insert(1, b"12345").unwrap();
+        let entries = || std::fs::read_dir(&dir).unwrap().count();
+        assert_eq!(entries(), 1);
+
+        // A fresh temporary file may be a write in progress, an old one is stale
+        let tmp = dir.join("2.torrent.1-1.tmp");
+        std::fs::write(&tmp, b"678").unwrap();
+        cache.evict().unwrap();
+        assert!(tmp.exists());
+        let old = SystemTime::now() - STALE_TMP_AGE;
+        std::fs::File::options()
+            .write(true)
+            .open(&tmp)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        cache.evict().unwrap();
+        assert!(!tmp.exists());
+        assert_eq!(cache.get(1), Some(b"12345".to_vec()));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_torrent_cache_age() {
+        let dir = cache_dir("torrent-cache-age");
+        let cache = TorrentCache::new(dir.clone(), 1024, Duration::ZERO).unwrap();
+        cache.insert(1, b"12345").unwrap();
+        assert_eq!(cache.get(1), None);
+        assert!(!dir.join("1.torrent").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,289 @@
+use crate::VERSION;
+use crate::categories::{Category, NEWZNAB_MAPPING, NEWZNAB_NAMES, newznab_categories};
+use crate::parser::Torrent;
+use crate::search::{MAX_LIMIT, PAGE_SIZE};
+use std::collections::BTreeSet;
+
+/// YGG category ids are exposed as Torznab custom categories so they never
+/// collide with the standard Newznab ranges (2000 = Movies, 5000 = TV, ...).
+pub const CUSTOM_CATEGORY_OFFSET: usize = 100000;
+
+pub fn to_torznab_category(ygg_id: usize) -> usize {
+    ygg_id + CUSTOM_CATEGORY_OFFSET
+}
+
+pub fn from_torznab_category(id: usize) -> usize {
+    if id >= CUSTOM_CATEGORY_OFFSET {
+        id - CUSTOM_CATEGORY_OFFSET
+    } else {
+        id
+    }
+}
+
+pub fn caps_xml(categories: &[Category], tmdb_enabled: bool) -> String {
+    let (tv_params, movie_params) = match tmdb_enabled {
+        true => ("q,season,ep,tvdbid,imdbid,tmdbid", "q,imdbid,tmdbid"),
+        false => ("q,season,ep", "q"),
+    };
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<caps>\n");
+    xml.push_str(&format!(
+        "  <server version=\"{}\" title=\"Ygégé\" />\n",
+        VERSION
+    ));
+    xml.push_str(&format!(
+        "  <limits default=\"{}\" max=\"{}\" />\n",
+        PAGE_SIZE, MAX_LIMIT
+    ));
+    xml.push_str("  <searching>\n");
+    xml.push_str("    <search available=\"yes\" supportedParams=\"q\" />\n");
+    xml.push_str(&format!(
+        "    <tv-search available=\"yes\" supportedParams=\"{}\" />\n",
+        tv_params
+    ));
+    xml.push_str(&format!(
+        "    <movie-search available=\"yes\" supportedParams=\"{}\" />\n",
+        movie_params
+    ));
+    xml.push_str("    <music-search available=\"yes\" supportedParams=\"q\" />\n");
+    xml.push_str("    <book-search available=\"yes\" supportedParams=\"q\" />\n");
+    xml.push_str("  </searching>\n");
+    xml.push_str("  <categories>\n");
+    // Standard Newznab categories, which searches accept too
+    let mapped: BTreeSet<usize> = NEWZNAB_MAPPING
+        .iter()
+        .flat_map(|(_, id, subs)| std::iter::once(*id).chain(subs.iter().map(|(_, id)| *id)))
+        .collect();
+    for (id, name) in NEWZNAB_NAMES
+        .iter()
+        .filter(|(id, _)| id.is_multiple_of(1000))
+    {
+        if !mapped.iter().any(|mapped| mapped / 1000 * 1000 == *id) {
+            continue;
+        }
+        xml.push_str(&format!(
+            "    <category id=\"{}\" name=\"{}\">\n",
+            id,
+            escape(name)
+        ));
+        let sub_categories = NEWZNAB_NAMES
+            .iter()
+            .filter(|(sub, _)| sub / 1000 * 1000 == *id && sub != id && mapped.contains(sub));
+        for (sub_id, sub_name) in sub_categories {
+            xml.push_str(&format!(
+                "      <subcat id=\"{}\" name=\"{}\" />\n",
+                sub_id,
+                escape(sub_name)
+            ));
+        }
+        xml.push_str("    </category>\n");
+    }
+    for category in categories {
+        xml.push_str(&format!(
+            "    <category id=\"{}\" name=\"{}\">\n",
+            to_torznab_category(category.id),
+            escape(&category.name)
+        ));
+        for sub_category in &category.sub_categories {
+            xml.push_str(&format!(
+                "      <subcat id=\"{}\" name=\"{}\" />\n",
+                to_torznab_category(sub_category.id),
+                escape(&sub_category.name)
+            ));
+        }
+        xml.push_str("    </category>\n");
+    }
+    xml.push_str("  </categories>\n</caps>\n");
+    xml
+}
+
+/// Torznab results. The `magneturl` attributes announce with the passkey of the
+/// shared account, so `with_magnet` is false for requests with custom cookies.
+pub fn results_xml(
+    torrents: &[Torrent],
+    categories: &[Category],
+    base_url: &str,
+    with_magnet: bool,
+) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\" xmlns:torznab=\"http://torznab.com/schemas/2015/feed\">\n");
+    xml.push_str("  <channel>\n");
+    xml.push_str("    <title>Ygégé</title>\n");
+    xml.push_str("    <description>Ygégé Torznab feed</description>\n");
+    xml.push_str(&format!("    <link>{}/</link>\n", escape(base_url)));
+    for torrent in torrents {
+        xml.push_str(&item_xml(torrent, categories, base_url, with_magnet));
+    }
+    xml.push_str("  </channel>\n</rss>\n");
+    xml
+}
+
+fn item_xml(
+    torrent: &Torrent,
+    categories: &[Category],
+    base_url: &str,
+    with_magnet: bool,
+) -> String {
+    let download = format!("{}/torrent/{}", base_url, torrent.id);
+    let guid = match torrent.link.is_empty() {
+        true => download.clone(),
+        false => torrent.link.clone(),
+    };
+
+    let mut category_ids = vec![to_torznab_category(torrent.category_id)];
+    if let Some(parent) = parent_category(categories, torrent.category_id) {
+        category_ids.push(to_torznab_category(parent));
+    }
+    category_ids.extend(newznab_categories(torrent.category_id));
+
+    let mut xml = String::from("    <item>\n");
+    xml.push_str(&format!("      <title>{}</title>\n", escape(&torrent.name)));
+    xml.push_str(&format!(
+        "      <guid isPermaLink=\"true\">{}</guid>\n",
+        escape(&guid)
+    ));
+    xml.push_str(&format!("      <link>{}</link>\n", escape(&download)));
+    xml.push_str(&format!("      <comments>{}</comments>\n", escape(&guid)));
+    xml.push_str(&format!(
+        "      <pubDate>{}</pubDate>\n",
+        pub_date(torrent.age_stamp)
+    ));
+    xml.push_str(&format!("      <size>{}</size>\n", torrent.size));
+    for id in &category_ids {
+        xml.push_str(&format!("      <category>{}</category>\n", id));
+    }
+    xml.push_str(&format!(
+        "      <enclosure url=\"{}\" length=\"{}\" type=\"application/x-bittorrent\" />\n",
+        escape(&download),
+        torrent.size
+    ));
+    for id in &category_ids {
+        xml.push_str(&attr_xml("category", &id.to_string()));
+    }
+    xml.push_str(&attr_xml("size", &torrent.size.to_string()));
+    xml.push_str(&attr_xml("seeders", &torrent.seed.to_string()));
+    xml.push_str(&attr_xml(
+        "peers",
+        &(torrent.seed + torrent.leech).to_string(),
+    ));
+    xml.push_str(&attr_xml("grabs", &torrent.completed.to_string()));
+    if let Some(infohash) = &torrent.infohash {
+        xml.push_str(&attr_xml("infohash", infohash));
+    }
+    if let Some(magnet) = torrent.get_magnet().filter(|_| with_magnet) {
+        xml.push_str(&attr_xml("magneturl", &magnet));
+    }
+    xml.push_str(&attr_xml("downloadvolumefactor", "1"));
+    xml.push_str(&attr_xml("uploadvolumefactor", "1"));
+    xml.push_str("    </item>\n");
+    xml
+}
+
+fn attr_xml(name: &str, value: &str) -> String {
+    format!(
+        "      <torznab:attr name=\"{}\" value=\"{}\" />\n",
+        name,
+        escape(value)
+    )
+}
+
+pub fn error_xml(code: u16, description: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<error code=\"{}\" description=\"{}\" />\n",
+        code,
+        escape(description)
+    )
+}
+
+/// Formats a unix timestamp as a RFC 2822 date, as used by `pubDate`.
+pub(crate) fn pub_date(timestamp: usize) -> String {
+    chrono::DateTime::from_timestamp(timestamp as i64, 0)
+        .unwrap_or_default()
+        .to_rfc2822()
+}
+
+pub(crate) fn parent_category(categories: &[Category], sub_category_id: usize) -> Option<usize> {
+    categories
+        .iter()
+        .find(|cat| {
+            cat.sub_categories
+                .iter()
+                .any(|sub_cat| sub_cat.id == sub_category_id)
+        })
+        .map(|cat| cat.id)
+}
+
+pub(crate) fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{categories, feed_torrent};
+
+    #[test]
+    fn test_category_offset() {
+        assert_eq!(to_torznab_category(2183), 102183);
+        assert_eq!(from_torznab_category(102183), 2183);
+        assert_eq!(from_torznab_category(2183), 2183);
+    }
+
+    #[test]
+    fn test_caps_xml() {
+        let xml = caps_xml(&categories(), false);
+        assert!(xml.contains("<category id=\"102145\" name=\"Film/Vidéo\">"));
+        assert!(xml.contains("<subcat id=\"102183\" name=\"Film\" />"));
+        assert!(xml.contains("<category id=\"2000\" name=\"Movies\">"));
+        assert!(xml.contains("<subcat id=\"5070\" name=\"TV/Anime\" />"));
+        assert!(xml.contains("<category id=\"8000\" name=\"Other\">"));
+        assert!(xml.contains("<movie-search available=\"yes\" supportedParams=\"q\" />"));
+
+        let xml = caps_xml(&categories(), true);
+        assert!(xml.contains("supportedParams=\"q,imdbid,tmdbid\""));
+        assert!(xml.contains("supportedParams=\"q,season,ep,tvdbid,imdbid,tmdbid\""));
+    }
+
+    #[test]
+    fn test_results_xml() {
+        let xml = results_xml(
+            &[feed_torrent()],
+            &categories(),
+            "http://localhost:8715",
+            true,
+        );
+        assert!(xml.contains("<title>Vaiana &amp; co &lt;2016&gt;</title>"));
+        assert!(xml.contains(
+            "<enclosure url=\"http://localhost:8715/torrent/1234\" length=\"3189013217\""
+        ));
+        assert!(xml.contains("<torznab:attr name=\"category\" value=\"102183\" />"));
+        assert!(xml.contains("<torznab:attr name=\"category\" value=\"102145\" />"));
+        assert!(xml.contains("<torznab:attr name=\"category\" value=\"2000\" />"));
+        assert!(xml.contains("<torznab:attr name=\"seeders\" value=\"10\" />"));
+        assert!(xml.contains("<torznab:attr name=\"peers\" value=\"13\" />"));
+        assert!(xml.contains("<torznab:attr name=\"grabs\" value=\"15\" />"));
+        assert!(xml.contains(
+            "<torznab:attr name=\"infohash\" value=\"0123456789abcdef0123456789abcdef01234567\" />"
+        ));
+        assert!(xml.contains("<pubDate>Tue, 28 Jan 2025 06:15:26 +0000</pubDate>"));
+    }
+
+    #[test]
+    fn test_error_xml() {
+        assert_eq!(
+            error_xml(202, "No such function \"foo\""),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<error code=\"202\" description=\"No such function &quot;foo&quot;\" />\n"
+        );
+    }
+}
@@ -16,6 +16,12 @@ pub struct YggResponse {
     pub url: String,
 }
 
+pub struct YggBytesResponse {
+    pub status: u16,
+    pub bytes: Vec<u8>,
+    pub url: String,
+}
+
 impl YggClient {
     fn session_ref(session_id: &str) -> Option<&str> {
         if session_id.is_empty() {
@@ -101,13 +107,21 @@ impl YggClient {
         }
     }
 
-    pub async fn get_bytes(&self, url: &str) -> Result<(u16, Vec<u8>), Box<dyn std::error::Error>> {
+    pub async fn get_bytes(
+        &self,
+        url: &str,
+    ) -> Result<YggBytesResponse, Box<dyn std::error::Error>> {
         match self {
             YggClient::Direct(client) => {
                 let response = client.get(url).send().await?;
                 let status = response.status().as_u16();
+                let final_url = response.url().to_string();
                 let bytes = response.bytes().await?.to_vec();
-                Ok((status, bytes))
+                Ok(YggBytesResponse {
+                    status,
+                    bytes,
+                    url: final_url,
+                })
             }
             YggClient::Proxied {
                 flaresolverr,
@@ -119,9 +133,11 @@ impl YggClient {
                 let solution = response
                     .solution
                     .ok_or("No solution in FlareSolverr response")?;
-                let status = solution.status;
-                let bytes = solution.response.into_bytes();
-                Ok((status, bytes))
+                Ok(YggBytesResponse {
+                    status: solution.status,
+                    bytes: solution.response.into_bytes(),
+                    url: solution.url,
+                })
             }
         }
     }